@@ -0,0 +1,76 @@
+//! `dump` command printing a day's parsed input as JSON.
+//!
+//! There is no shared binary: each day handles the command in its own
+//! entry point, run as `<day> dump < input`. Include with
+//! `#[path = "../../common/dump.rs"] mod dump;` and start the day's `main`
+//! with `dump::main(dump)`, `dump` turning the raw input into JSON. It
+//! returns when the day is run without arguments, so that the day goes on
+//! as usual.
+
+#![allow(dead_code)]
+
+use serde_json::Value as Json;
+use std::io::{Read, Write};
+
+const USAGE: &str = "usage: dump < input";
+
+/// Reads `input` and writes its dump to `out` if `args` (without the
+/// program name) ask for it. `Ok(false)` when there are no arguments,
+/// the usage for anything else.
+pub fn run(
+    args: &[String],
+    mut input: impl Read,
+    mut out: impl Write,
+    dump: impl FnOnce(&str) -> Json,
+) -> Result<bool, String> {
+    match args {
+        [] => Ok(false),
+        [cmd] if cmd == "dump" => {
+            let mut text = String::new();
+            input
+                .read_to_string(&mut text)
+                .map_err(|e| format!("reading input: {e}"))?;
+            writeln!(out, "{:#}", dump(&text)).map_err(|e| format!("writing dump: {e}"))?;
+            Ok(true)
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// `run` on the process' arguments and standard streams. Exits once the
+/// dump is written, with status 2 on failure.
+pub fn main(dump: impl FnOnce(&str) -> Json) {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args, std::io::stdin(), std::io::stdout(), dump) {
+        Ok(false) => {}
+        Ok(true) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The JSON `run` writes for `input`, for tests.
+pub fn dumped(input: &str, dump: impl FnOnce(&str) -> Json) -> Json {
+    let mut out = Vec::new();
+    assert_eq!(
+        run(&["dump".to_owned()], input.as_bytes(), &mut out, dump),
+        Ok(true)
+    );
+    serde_json::from_slice(&out).unwrap()
+}
+
+#[test]
+fn dump_command() {
+    let echo = |s: &str| Json::String(s.to_owned());
+    assert_eq!(dumped("abc", echo), echo("abc"));
+
+    let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let run = |a: &[&str]| run(&args(a), &b""[..], Vec::new(), echo);
+    assert_eq!(run(&["dump"]), Ok(true));
+    assert_eq!(run(&[]), Ok(false));
+    let usage = Err(USAGE.to_owned());
+    assert_eq!(run(&["dump", "2023", "8"]), usage);
+    assert_eq!(run(&["-"]), usage);
+}
@@ -0,0 +1,31 @@
+//! Streaming line-oriented input.
+//!
+//! Include with `#[path = "../../common/lines.rs"] mod lines;`. Unlike
+//! `BufRead::lines` this reuses a single buffer, so memory stays bounded by
+//! the longest line no matter how large the input is.
+
+#![allow(dead_code)]
+
+use std::io::{self, BufRead};
+
+/// Calls `f` on every line of `reader` with the line terminator (`\n` or
+/// `\r\n`) stripped.
+pub fn for_each_line<R: BufRead>(mut reader: R, mut f: impl FnMut(&str)) -> io::Result<()> {
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            return Ok(());
+        }
+        let line = buf.strip_suffix('\n').unwrap_or(&buf);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        f(line);
+    }
+}
+
+#[test]
+fn strips_terminators() {
+    let mut seen = Vec::new();
+    for_each_line(&b"a\r\n\nbc\nd"[..], |l| seen.push(l.to_owned())).unwrap();
+    assert_eq!(seen, ["a", "", "bc", "d"]);
+}
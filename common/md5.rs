@@ -0,0 +1,154 @@
+//! MD5 (RFC 1321) without dependencies.
+//!
+//! Include with `#[path = "../../common/md5.rs"] mod md5;`. `digest` hashes a
+//! whole message. `Md5` hashes one fed in pieces, and can be cloned part way
+//! to hash many messages sharing a prefix without redoing it.
+
+#![allow(dead_code)]
+
+const S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, //
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, //
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, //
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// `floor(abs(sin(i + 1)) * 2^32)`.
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+pub type Digest = [u8; 16];
+
+#[derive(Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    /// Bytes of an incomplete block.
+    buf: [u8; 64],
+    len: u64,
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Md5 {
+    pub fn new() -> Self {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buf: [0; 64],
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        let used = (self.len % 64) as usize;
+        self.len += data.len() as u64;
+
+        if used > 0 {
+            let n = data.len().min(64 - used);
+            self.buf[used..used + n].copy_from_slice(&data[..n]);
+            data = &data[n..];
+            if used + n < 64 {
+                return;
+            }
+            let block = self.buf;
+            self.compress(&block);
+        }
+
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block.try_into().unwrap());
+        }
+        let rest = blocks.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+    }
+
+    pub fn finish(mut self) -> Digest {
+        let bits = self.len.wrapping_mul(8);
+        let used = (self.len % 64) as usize;
+        let pad = if used < 56 { 56 - used } else { 120 - used };
+        let mut padding = [0; 72];
+        padding[0] = 0x80;
+        padding[pad..pad + 8].copy_from_slice(&bits.to_le_bytes());
+        self.update(&padding[..pad + 8]);
+        debug_assert_eq!(self.len % 64, 0);
+
+        let mut ret = [0; 16];
+        for (out, word) in ret.chunks_exact_mut(4).zip(self.state) {
+            out.copy_from_slice(&word.to_le_bytes());
+        }
+        ret
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let m: [u32; 16] =
+            std::array::from_fn(|i| u32::from_le_bytes(block[4 * i..][..4].try_into().unwrap()));
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+        for (s, x) in self.state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(x);
+        }
+    }
+}
+
+pub fn digest(data: &[u8]) -> Digest {
+    let mut md5 = Md5::new();
+    md5.update(data);
+    md5.finish()
+}
+
+pub fn to_hex(digest: &Digest) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[test]
+fn rfc_1321_vectors() {
+    let hex = |s: &str| to_hex(&digest(s.as_bytes()));
+    assert_eq!(hex(""), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(hex("a"), "0cc175b9c0f1b6a831c399e269772661");
+    assert_eq!(hex("abc"), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(hex("message digest"), "f96b697d7cb7938d525a2f31aaf161d0");
+    assert_eq!(
+        hex("abcdefghijklmnopqrstuvwxyz"),
+        "c3fcd3d76192e4007dfb496cca67e13b"
+    );
+    assert_eq!(
+        hex("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+        "d174ab98d277d9f5a5611c2c9f419d9f"
+    );
+    assert_eq!(
+        hex(&"1234567890".repeat(8)),
+        "57edf4a22be3c955ac49da2e2107b67a"
+    );
+
+    // pieces of every size give the same digest
+    let data = (0..300).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+    let whole = digest(&data);
+    for piece in [1, 3, 55, 56, 63, 64, 65, 128] {
+        let mut md5 = Md5::new();
+        data.chunks(piece).for_each(|c| md5.update(c));
+        assert_eq!(md5.finish(), whole, "{piece}");
+    }
+}
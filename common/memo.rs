@@ -0,0 +1,235 @@
+//! Memoisation for recursive solvers.
+//!
+//! Include with `#[path = "../../common/memo.rs"] mod memo;`. `Memo` is a
+//! cache with hit/miss counters on top of either a `HashMap` or, for small
+//! integer keys, a flat `Vec` (`Memo::dense`). Recursive functions take
+//! `&mut Memo` and go through `get_or_insert_with`; `memoize` wraps a
+//! closure that recurses through the function it is given.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+pub trait Store<K, V> {
+    fn lookup(&self, key: &K) -> Option<&V>;
+    fn store(&mut self, key: K, value: V);
+    fn clear(&mut self);
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Hash + Eq, V> Store<K, V> for HashMap<K, V> {
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+
+    fn store(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+pub trait DenseKey {
+    fn index(&self) -> usize;
+}
+
+macro_rules! dense_key {
+    ($($t:ty),*) => {
+        $(impl DenseKey for $t {
+            fn index(&self) -> usize {
+                usize::try_from(*self).unwrap()
+            }
+        })*
+    };
+}
+
+dense_key!(u8, u16, u32, u64, usize);
+
+pub struct Dense<V> {
+    slots: Vec<Option<V>>,
+    len: usize,
+}
+
+impl<K: DenseKey, V> Store<K, V> for Dense<V> {
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.slots.get(key.index())?.as_ref()
+    }
+
+    fn store(&mut self, key: K, value: V) {
+        let idx = key.index();
+        if idx >= self.slots.len() {
+            self.slots.resize_with(idx + 1, || None);
+        }
+        if self.slots[idx].replace(value).is_none() {
+            self.len += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|s| *s = None);
+        self.len = 0;
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+pub struct Memo<K, V, S = HashMap<K, V>> {
+    store: S,
+    stats: Stats,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: Hash + Eq, V> Memo<K, V> {
+    pub fn new() -> Self {
+        Self::with_store(HashMap::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: DenseKey, V> Memo<K, V, Dense<V>> {
+    /// Keys are expected to be below `size`, larger ones grow the table.
+    pub fn dense(size: usize) -> Self {
+        Self::with_store(Dense {
+            slots: (0..size).map(|_| None).collect(),
+            len: 0,
+        })
+    }
+}
+
+impl<K, V, S: Store<K, V>> Memo<K, V, S> {
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            stats: Stats::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let ret = self.store.lookup(key);
+        if ret.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        ret
+    }
+
+    /// Like `get(..).is_some()`, without counting towards the statistics.
+    pub fn contains(&self, key: &K) -> bool {
+        self.store.lookup(key).is_some()
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.store.store(key, value);
+    }
+
+    /// Returns the cached value for `key` or computes it with `f`, which may
+    /// recurse through the same cache.
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce(&mut Self) -> V) -> V
+    where
+        V: Clone,
+    {
+        if let Some(v) = self.get(&key) {
+            return v.clone();
+        }
+        let v = f(self);
+        self.insert(key, v.clone());
+        v
+    }
+
+    /// Drops cached values, statistics are kept.
+    pub fn clear(&mut self) {
+        self.store.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+}
+
+/// Evaluates a recursive function `f` at `key`; `f` gets a memoised version
+/// of itself as its first argument.
+pub fn memoize<K, V, F>(key: K, f: &F) -> V
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    F: Fn(&mut dyn FnMut(K) -> V, K) -> V,
+{
+    fn go<K, V, F>(memo: &mut Memo<K, V>, key: K, f: &F) -> V
+    where
+        K: Hash + Eq + Clone,
+        V: Clone,
+        F: Fn(&mut dyn FnMut(K) -> V, K) -> V,
+    {
+        memo.get_or_insert_with(key.clone(), |memo| f(&mut |k| go(memo, k, f), key))
+    }
+
+    go(&mut Memo::new(), key, f)
+}
+
+#[test]
+fn memo_backends() {
+    fn fib<S: Store<u64, u64>>(n: u64, memo: &mut Memo<u64, u64, S>) -> u64 {
+        if n < 2 {
+            return n;
+        }
+        memo.get_or_insert_with(n, |memo| fib(n - 1, memo) + fib(n - 2, memo))
+    }
+
+    let mut hashed = Memo::new();
+    assert_eq!(fib(90, &mut hashed), 2880067194370816120);
+    let mut dense = Memo::dense(16);
+    assert_eq!(fib(90, &mut dense), 2880067194370816120);
+    assert_eq!(dense.len(), 89);
+    assert_eq!(dense.stats(), hashed.stats());
+    assert_eq!(dense.stats(), Stats { hits: 87, misses: 89 });
+
+    assert!(dense.contains(&10));
+    assert_eq!(dense.stats(), hashed.stats());
+
+    dense.clear();
+    assert!(dense.is_empty());
+    assert!(!dense.contains(&10));
+    assert_eq!(dense.get(&10), None);
+
+    let fib = |n| memoize(n, &|fib, n: u64| if n < 2 { n } else { fib(n - 1) + fib(n - 2) });
+    assert_eq!(fib(90), 2880067194370816120);
+}
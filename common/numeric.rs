@@ -0,0 +1,501 @@
+//! Overflow-safe arithmetic.
+//!
+//! Include with `#[path = "../../common/numeric.rs"] mod numeric;`.
+//! `Checked<T>` behaves like the wrapped integer, except that every
+//! operation panics with the offending operands on overflow, in release
+//! builds too. `U256` is a fixed-width integer for inputs that do not fit
+//! in 128 bits.
+//!
+//! Days switch to these with `--cfg aoc_checked` (`Checked` over the usual
+//! type) or `--cfg aoc_wide` (`Checked` over a wider one), e.g.
+//! `RUSTFLAGS="--cfg aoc_checked" cargo test --release`.
+
+#![allow(dead_code)]
+
+use std::fmt::{self, Debug, Display};
+use std::iter::{Product, Sum};
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, Div, DivAssign, Mul, MulAssign, Not, Rem, RemAssign, Sub,
+    SubAssign,
+};
+use std::str::FromStr;
+
+pub trait Int: Copy + Ord + Display {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    fn checked_shl(self, n: u32) -> Option<Self>;
+    fn checked_shr(self, n: u32) -> Option<Self>;
+}
+
+macro_rules! int_impl {
+    ($($t:ty),*) => {
+        $(impl Int for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+
+            fn checked_div(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_div(self, rhs)
+            }
+
+            fn checked_rem(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_rem(self, rhs)
+            }
+
+            fn checked_shl(self, n: u32) -> Option<Self> {
+                <$t>::checked_shl(self, n)
+            }
+
+            fn checked_shr(self, n: u32) -> Option<Self> {
+                <$t>::checked_shr(self, n)
+            }
+        })*
+    };
+}
+
+int_impl!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128, usize);
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checked<T>(pub T);
+
+impl<T> Checked<T> {
+    pub fn get(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Int> Checked<T> {
+    pub fn checked_shl(self, n: u32) -> Option<Self> {
+        self.0.checked_shl(n).map(Self)
+    }
+
+    pub fn checked_shr(self, n: u32) -> Option<Self> {
+        self.0.checked_shr(n).map(Self)
+    }
+}
+
+#[cold]
+#[track_caller]
+fn overflow<T: Int>(lhs: T, op: &str, rhs: T) -> ! {
+    if matches!(op, "/" | "%") && rhs == T::ZERO {
+        panic!("division by zero: {lhs} {op} {rhs}")
+    }
+    panic!("arithmetic overflow: {lhs} {op} {rhs}")
+}
+
+macro_rules! checked_op {
+    ($Op:ident, $op:ident, $OpAssign:ident, $op_assign:ident, $checked:ident, $sym:literal) => {
+        impl<T: Int> $Op for Checked<T> {
+            type Output = Self;
+
+            #[track_caller]
+            fn $op(self, rhs: Self) -> Self {
+                match self.0.$checked(rhs.0) {
+                    Some(v) => Self(v),
+                    None => overflow(self.0, $sym, rhs.0),
+                }
+            }
+        }
+
+        impl<T: Int> $OpAssign for Checked<T> {
+            #[track_caller]
+            fn $op_assign(&mut self, rhs: Self) {
+                *self = $Op::$op(*self, rhs);
+            }
+        }
+    };
+}
+
+checked_op!(Add, add, AddAssign, add_assign, checked_add, "+");
+checked_op!(Sub, sub, SubAssign, sub_assign, checked_sub, "-");
+checked_op!(Mul, mul, MulAssign, mul_assign, checked_mul, "*");
+checked_op!(Div, div, DivAssign, div_assign, checked_div, "/");
+checked_op!(Rem, rem, RemAssign, rem_assign, checked_rem, "%");
+
+impl<T: BitAnd<Output = T>> BitAnd for Checked<T> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl<T: BitOr<Output = T>> BitOr for Checked<T> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl<T: Not<Output = T>> Not for Checked<T> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl<T: Int> Sum for Checked<T> {
+    #[track_caller]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self(T::ZERO), Add::add)
+    }
+}
+
+impl<T: Int> Product for Checked<T> {
+    #[track_caller]
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self(T::ONE), Mul::mul)
+    }
+}
+
+impl<T: Debug> Debug for Checked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Display> Display for Checked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: FromStr> FromStr for Checked<T> {
+    type Err = T::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// `From` for every lossless conversion days rely on, so that
+/// `Num::from(10u8)` compiles whatever `Num` is switched to.
+macro_rules! checked_from {
+    ($($t:ty: $($from:ty),*;)*) => {
+        $($(impl From<$from> for Checked<$t> {
+            fn from(n: $from) -> Self {
+                Self(<$t>::from(n))
+            }
+        })*)*
+    };
+}
+
+checked_from! {
+    u16: u8, u16;
+    u32: u8, u16, u32;
+    u64: u8, u16, u32, u64;
+    i32: u8, i8, i16, i32;
+    i64: u8, i8, i16, i32, i64;
+    i128: u8, i8, i16, i32, i64, i128;
+    U256: u8, u16, u32, u64, u128, U256;
+}
+
+/// Unsigned 256-bit integer, little-endian 64-bit limbs.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct U256([u64; 4]);
+
+impl U256 {
+    pub const MAX: Self = Self([u64::MAX; 4]);
+
+    fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    fn bit(&self, i: u32) -> bool {
+        self.0[i as usize / 64] >> (i % 64) & 1 == 1
+    }
+
+    fn bits(&self) -> u32 {
+        (0..4)
+            .rev()
+            .find(|&i| self.0[i] != 0)
+            .map_or(0, |i| 64 * i as u32 + 64 - self.0[i].leading_zeros())
+    }
+
+    fn wrapping_sub(self, rhs: Self) -> (Self, bool) {
+        let mut ret = [0; 4];
+        let mut borrow = false;
+        for (i, out) in ret.iter_mut().enumerate() {
+            let (d, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (d, b2) = d.overflowing_sub(borrow as u64);
+            *out = d;
+            borrow = b1 || b2;
+        }
+        (Self(ret), borrow)
+    }
+
+    fn div_rem(self, rhs: Self) -> Option<(Self, Self)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let mut quotient = Self::default();
+        let mut rem = Self::default();
+        for i in (0..self.bits()).rev() {
+            // the bit shifted out matters when `rhs` is above 2^255
+            let carry = rem.bit(255);
+            rem = rem.checked_shl(1).unwrap();
+            rem.0[0] |= self.bit(i) as u64;
+            if carry || rem >= rhs {
+                rem = rem.wrapping_sub(rhs).0;
+                quotient.0[i as usize / 64] |= 1 << (i % 64);
+            }
+        }
+        Some((quotient, rem))
+    }
+
+    fn div_rem_u64(self, rhs: u64) -> (Self, u64) {
+        let mut ret = [0; 4];
+        let mut rem = 0u128;
+        for i in (0..4).rev() {
+            let cur = (rem << 64) | u128::from(self.0[i]);
+            ret[i] = (cur / u128::from(rhs)) as u64;
+            rem = cur % u128::from(rhs);
+        }
+        (Self(ret), rem as u64)
+    }
+}
+
+impl Int for U256 {
+    const ZERO: Self = Self([0; 4]);
+    const ONE: Self = Self([1, 0, 0, 0]);
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        let mut ret = [0; 4];
+        let mut carry = false;
+        for (i, out) in ret.iter_mut().enumerate() {
+            let (s, c1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (s, c2) = s.overflowing_add(carry as u64);
+            *out = s;
+            carry = c1 || c2;
+        }
+        (!carry).then_some(Self(ret))
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        let (ret, borrow) = self.wrapping_sub(rhs);
+        (!borrow).then_some(ret)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let mut ret = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let cur =
+                    u128::from(self.0[i]) * u128::from(rhs.0[j]) + u128::from(ret[i + j]) + carry;
+                ret[i + j] = cur as u64;
+                carry = cur >> 64;
+            }
+            ret[i + 4] = carry as u64;
+        }
+        let (low, high) = ret.split_at(4);
+        high.iter()
+            .all(|&limb| limb == 0)
+            .then(|| Self(low.try_into().unwrap()))
+    }
+
+    fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.div_rem(rhs).map(|(q, _)| q)
+    }
+
+    fn checked_rem(self, rhs: Self) -> Option<Self> {
+        self.div_rem(rhs).map(|(_, r)| r)
+    }
+
+    fn checked_shl(self, n: u32) -> Option<Self> {
+        if n >= 256 {
+            return None;
+        }
+        let (limbs, bits) = ((n / 64) as usize, n % 64);
+        let mut ret = [0; 4];
+        for (i, out) in ret.iter_mut().enumerate().skip(limbs) {
+            *out = self.0[i - limbs] << bits;
+            if bits != 0 && i > limbs {
+                *out |= self.0[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        Some(Self(ret))
+    }
+
+    fn checked_shr(self, n: u32) -> Option<Self> {
+        if n >= 256 {
+            return None;
+        }
+        let (limbs, bits) = ((n / 64) as usize, n % 64);
+        let mut ret = [0; 4];
+        for (i, out) in ret.iter_mut().take(4 - limbs).enumerate() {
+            *out = self.0[i + limbs] >> bits;
+            if bits != 0 && i + limbs + 1 < 4 {
+                *out |= self.0[i + limbs + 1] << (64 - bits);
+            }
+        }
+        Some(Self(ret))
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+macro_rules! u256_from {
+    ($($t:ty),*) => {
+        $(impl From<$t> for U256 {
+            fn from(n: $t) -> Self {
+                let n = u128::from(n);
+                Self([n as u64, (n >> 64) as u64, 0, 0])
+            }
+        })*
+    };
+}
+
+u256_from!(u8, u16, u32, u64, u128);
+
+impl Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        loop {
+            let (q, r) = n.div_rem_u64(CHUNK);
+            chunks.push(r);
+            n = q;
+            if n.is_zero() {
+                break;
+            }
+        }
+        let (first, rest) = chunks.split_last().unwrap();
+        let mut s = first.to_string();
+        rest.iter().rev().for_each(|c| s += &format!("{c:019}"));
+        f.pad_integral(true, "", &s)
+    }
+}
+
+impl Debug for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseU256Error {
+    Empty,
+    InvalidDigit,
+    Overflow,
+}
+
+impl FromStr for U256 {
+    type Err = ParseU256Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(ParseU256Error::Empty);
+        }
+        s.bytes().try_fold(Self::ZERO, |acc, ch| {
+            let digit = ch
+                .is_ascii_digit()
+                .then(|| Self::from(ch - b'0'))
+                .ok_or(ParseU256Error::InvalidDigit)?;
+            acc.checked_mul(Self::from(10u8))
+                .and_then(|acc| acc.checked_add(digit))
+                .ok_or(ParseU256Error::Overflow)
+        })
+    }
+}
+
+#[test]
+fn u256_arithmetic() {
+    let big = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    assert_eq!(U256::MAX.to_string(), big);
+    assert_eq!(big.parse::<U256>(), Ok(U256::MAX));
+    assert_eq!(
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            .parse::<U256>(),
+        Err(ParseU256Error::Overflow)
+    );
+
+    let a = U256::from(u128::MAX);
+    let square = a.checked_mul(a).unwrap();
+    assert_eq!(
+        square.to_string(),
+        "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+    );
+    assert_eq!(square.checked_div(a), Some(a));
+    assert_eq!(
+        square.checked_rem(U256::from(1_000_000u32)),
+        Some(U256::from(217_025u32))
+    );
+    assert_eq!(square.checked_mul(U256::from(2u8)), None);
+    let half = U256::ONE.checked_shl(255).unwrap();
+    let three_halves = half.checked_add(half.checked_shr(1).unwrap()).unwrap();
+    assert_eq!(
+        U256::MAX.checked_rem(three_halves),
+        U256::MAX.checked_sub(three_halves)
+    );
+    assert_eq!(
+        U256::ONE.checked_shl(200).unwrap().checked_shr(200),
+        Some(U256::ONE)
+    );
+    assert_eq!(U256::ZERO.checked_sub(U256::ONE), None);
+}
+
+#[test]
+fn checked_panics_on_overflow() {
+    let sum = std::panic::catch_unwind(|| Checked(u8::MAX) + Checked(1));
+    let msg = sum.unwrap_err();
+    assert_eq!(
+        msg.downcast_ref::<String>().unwrap(),
+        "arithmetic overflow: 255 + 1"
+    );
+    for (result, expected) in [
+        (
+            std::panic::catch_unwind(|| Checked(7i32) / Checked(0)),
+            "division by zero: 7 / 0",
+        ),
+        (
+            std::panic::catch_unwind(|| Checked(7i32) % Checked(0)),
+            "division by zero: 7 % 0",
+        ),
+        (
+            std::panic::catch_unwind(|| Checked(i32::MIN) / Checked(-1)),
+            "arithmetic overflow: -2147483648 / -1",
+        ),
+    ] {
+        let msg = result.unwrap_err();
+        assert_eq!(msg.downcast_ref::<String>().unwrap(), expected);
+    }
+    assert_eq!(
+        [2, 3, 7]
+            .map(Checked::<u64>)
+            .into_iter()
+            .product::<Checked<_>>(),
+        Checked(42)
+    );
+}
@@ -0,0 +1,221 @@
+//! Step tracing for simulations.
+//!
+//! Include with `#[path = "../../common/trace.rs"] mod trace;` and thread
+//! `&mut impl Tracer` through the step function. `NoTrace` compiles down to
+//! nothing, `Printer` is what entry points build from command-line flags:
+//!
+//! * `--trace` prints every event;
+//! * `--trace=kind1,kind2` prints only events of the listed kinds;
+//! * `--trace-json` (optionally with the above) writes events as JSON lines.
+//!
+//! Events go to stderr, so the answers on stdout stay diffable.
+
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+
+#[derive(Clone, Copy)]
+pub enum Value<'a> {
+    Int(i128),
+    Str(&'a str),
+    /// A number of a type not convertible to `i128`, written unquoted.
+    Num(&'a dyn std::fmt::Display),
+}
+
+macro_rules! value_from_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Value<'_> {
+            fn from(n: $t) -> Self {
+                Self::Int(n as i128)
+            }
+        })*
+    };
+}
+
+value_from_int!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(s: &'a str) -> Self {
+        Self::Str(s)
+    }
+}
+
+pub struct Event<'a> {
+    pub kind: &'static str,
+    pub fields: &'a [(&'static str, Value<'a>)],
+}
+
+pub trait Tracer {
+    fn event(&mut self, event: &Event<'_>);
+
+    fn emit(&mut self, kind: &'static str, fields: &[(&'static str, Value<'_>)]) {
+        self.event(&Event { kind, fields });
+    }
+}
+
+pub struct NoTrace;
+
+impl Tracer for NoTrace {
+    #[inline(always)]
+    fn event(&mut self, _: &Event<'_>) {}
+}
+
+impl<T: Tracer> Tracer for Option<T> {
+    fn event(&mut self, event: &Event<'_>) {
+        if let Some(t) = self {
+            t.event(event);
+        }
+    }
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+    fn event(&mut self, event: &Event<'_>) {
+        (**self).event(event);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+pub struct Printer<W> {
+    out: W,
+    kinds: Option<Vec<String>>,
+    format: Format,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(out: W, kinds: Option<Vec<String>>, format: Format) -> Self {
+        Self { out, kinds, format }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn accepts(&self, kind: &str) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.iter().any(|k| k == kind))
+    }
+
+    fn write_text(&mut self, event: &Event<'_>) -> io::Result<()> {
+        write!(self.out, "{}", event.kind)?;
+        for (key, value) in event.fields {
+            match value {
+                Value::Int(n) => write!(self.out, " {key}={n}")?,
+                Value::Str(s) => write!(self.out, " {key}={s}")?,
+                Value::Num(n) => write!(self.out, " {key}={n}")?,
+            }
+        }
+        writeln!(self.out)
+    }
+
+    fn write_json(&mut self, event: &Event<'_>) -> io::Result<()> {
+        write!(self.out, "{{\"kind\":")?;
+        write_json_str(&mut self.out, event.kind)?;
+        for (key, value) in event.fields {
+            write!(self.out, ",")?;
+            write_json_str(&mut self.out, key)?;
+            write!(self.out, ":")?;
+            match value {
+                Value::Int(n) => write!(self.out, "{n}")?,
+                Value::Str(s) => write_json_str(&mut self.out, s)?,
+                Value::Num(n) => write!(self.out, "{n}")?,
+            }
+        }
+        writeln!(self.out, "}}")
+    }
+}
+
+fn write_json_str(out: &mut impl Write, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            ch if ch.is_control() => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => write!(out, "{ch}")?,
+        }
+    }
+    write!(out, "\"")
+}
+
+impl<W: Write> Tracer for Printer<W> {
+    fn event(&mut self, event: &Event<'_>) {
+        if !self.accepts(event.kind) {
+            return;
+        }
+        // a closed stream must not stop the solver
+        let _ = match self.format {
+            Format::Text => self.write_text(event),
+            Format::Json => self.write_json(event),
+        };
+    }
+}
+
+impl Printer<io::Stderr> {
+    /// Builds a printer from `--trace`/`--trace=<kinds>`/`--trace-json`.
+    /// Returns `None` if tracing was not requested.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut enabled = false;
+        let mut kinds = None;
+        let mut format = Format::Text;
+
+        for arg in args {
+            if arg == "--trace" {
+                enabled = true;
+            } else if let Some(list) = arg.strip_prefix("--trace=") {
+                enabled = true;
+                kinds = Some(list.split(',').map(str::to_owned).collect());
+            } else if arg == "--trace-json" {
+                enabled = true;
+                format = Format::Json;
+            }
+        }
+
+        enabled.then(|| Self::new(io::stderr(), kinds, format))
+    }
+}
+
+/// Entry point for days taking their input on stdin: runs `solve` with the
+/// printer asked for on the command line and prints its answers.
+pub fn main<T: Debug>(solve: impl FnOnce(&str, &mut Option<Printer<io::Stderr>>) -> T) {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .expect("failed to read input");
+    let mut tracer = Printer::from_args(std::env::args().skip(1));
+    println!("{:?}", solve(&input, &mut tracer));
+}
+
+#[test]
+fn printer_formats() {
+    let mut p = Printer::new(Vec::new(), None, Format::Text);
+    p.emit("tick", &[("x", 5.into()), ("dir", "Up".into())]);
+    assert_eq!(p.into_inner(), b"tick x=5 dir=Up\n");
+
+    let mut p = Printer::new(Vec::new(), Some(vec!["rest".into()]), Format::Json);
+    p.emit("tick", &[("x", 5.into())]);
+    p.emit("rest", &[("y", (-1).into()), ("name", "a\"b".into())]);
+    assert_eq!(
+        p.into_inner(),
+        b"{\"kind\":\"rest\",\"y\":-1,\"name\":\"a\\\"b\"}\n"
+    );
+
+    struct Closed;
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut p = Printer::new(Closed, None, Format::Text);
+    p.emit("tick", &[("x", 5.into())]);
+}
@@ -0,0 +1,48 @@
+#![cfg(test)]
+
+type Floor = i64;
+
+fn moves(input: &str) -> impl Iterator<Item = Floor> + '_ {
+    input
+        .bytes()
+        .filter(|ch| !ch.is_ascii_whitespace())
+        .map(|ch| match ch {
+            b'(' => 1,
+            b')' => -1,
+            _ => panic!("wrong character {}", char::from(ch)),
+        })
+}
+
+/// The final floor, and the position of the first instruction taking Santa
+/// into the basement if any does.
+fn solve_both(input: &str) -> (Floor, Option<usize>) {
+    let mut floor = 0;
+    let mut basement = None;
+    for (i, delta) in moves(input).enumerate() {
+        floor += delta;
+        if floor < 0 && basement.is_none() {
+            basement = Some(i + 1);
+        }
+    }
+    (floor, basement)
+}
+
+#[test]
+fn example() {
+    for (input, floor) in [
+        ("(())", 0),
+        ("()()", 0),
+        ("(((", 3),
+        ("(()(()(", 3),
+        ("))(((((", 3),
+        ("())", -1),
+        ("))(", -1),
+        (")))", -3),
+        (")())())", -3),
+    ] {
+        assert_eq!(solve_both(input).0, floor, "{input}");
+    }
+    assert_eq!(solve_both(")"), (-1, Some(1)));
+    assert_eq!(solve_both("()())\n"), (-1, Some(5)));
+    assert_eq!(solve_both("(()"), (1, None));
+}
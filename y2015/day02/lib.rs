@@ -0,0 +1,46 @@
+#![cfg(test)]
+
+type Len = u64;
+
+struct Present {
+    /// Sorted, shortest first.
+    dims: [Len; 3],
+}
+
+impl Present {
+    fn parse(s: &str) -> Self {
+        let mut dims = s.split('x').map(|d| d.parse().unwrap());
+        let mut dims: [Len; 3] = std::array::from_fn(|_| dims.next().unwrap());
+        dims.sort_unstable();
+        Self { dims }
+    }
+
+    /// Surface plus the area of the smallest side as slack.
+    fn paper(&self) -> Len {
+        let [a, b, c] = self.dims;
+        2 * (a * b + b * c + c * a) + a * b
+    }
+
+    /// Smallest perimeter of a face plus the volume for the bow.
+    fn ribbon(&self) -> Len {
+        let [a, b, c] = self.dims;
+        2 * (a + b) + a * b * c
+    }
+}
+
+fn solve_both(input: &str) -> (Len, Len) {
+    input
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(Present::parse)
+        .fold((0, 0), |(paper, ribbon), p| {
+            (paper + p.paper(), ribbon + p.ribbon())
+        })
+}
+
+#[test]
+fn example() {
+    assert_eq!(solve_both("2x3x4"), (58, 34));
+    assert_eq!(solve_both("1x1x10"), (43, 14));
+    assert_eq!(solve_both("\n2x3x4\n1x1x10\n"), (101, 48));
+}
@@ -0,0 +1,49 @@
+#![cfg(test)]
+
+use std::collections::HashSet;
+
+type Pos = (i32, i32);
+
+fn direction(ch: u8) -> Pos {
+    match ch {
+        b'^' => (-1, 0),
+        b'v' => (1, 0),
+        b'<' => (0, -1),
+        b'>' => (0, 1),
+        _ => panic!("wrong character {}", char::from(ch)),
+    }
+}
+
+/// Houses getting at least one present when `n_santas` take turns following
+/// the directions, all starting at the same house.
+fn n_visited(input: &str, n_santas: usize) -> usize {
+    assert_ne!(n_santas, 0);
+    let mut santas = vec![(0, 0); n_santas];
+    let mut visited = HashSet::from([(0, 0)]);
+    let directions = input.bytes().filter(|ch| !ch.is_ascii_whitespace());
+    for (i, ch) in directions.enumerate() {
+        let (dr, dc) = direction(ch);
+        let santa = &mut santas[i % n_santas];
+        *santa = (santa.0 + dr, santa.1 + dc);
+        visited.insert(*santa);
+    }
+    visited.len()
+}
+
+fn solve_both(input: &str) -> (usize, usize) {
+    (n_visited(input, 1), n_visited(input, 2))
+}
+
+#[test]
+fn example() {
+    assert_eq!(n_visited(">", 1), 2);
+    assert_eq!(n_visited("^>v<", 1), 4);
+    assert_eq!(n_visited("^v^v^v^v^v", 1), 2);
+
+    assert_eq!(n_visited("^v", 2), 3);
+    assert_eq!(n_visited("^>v<", 2), 3);
+    assert_eq!(n_visited("^v^v^v^v^v", 2), 11);
+
+    assert_eq!(solve_both("^>v<\n"), (4, 3));
+    assert_eq!(n_visited(">>>>>>", 3), 3);
+}
@@ -0,0 +1,86 @@
+#![cfg(test)]
+
+#[path = "../../common/md5.rs"]
+mod md5;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+type Nonce = u64;
+
+/// Nonces each thread takes at a time.
+const BATCH: Nonce = 4096;
+
+/// Whether the hex form of `digest` starts with `n_zeros` zeros.
+fn has_leading_zeros(digest: &md5::Digest, n_zeros: usize) -> bool {
+    assert!(n_zeros <= 2 * digest.len());
+    let (full, half) = (n_zeros / 2, n_zeros % 2);
+    digest[..full].iter().all(|&b| b == 0) && (half == 0 || digest[full] >> 4 == 0)
+}
+
+/// Lowest positive nonce which hashed after `key` gives `n_zeros` leading
+/// hex zeros. Threads take batches of nonces in increasing order, and stop
+/// once every nonce below the best found so far is taken.
+fn mine(key: &str, n_zeros: usize, n_threads: usize) -> Nonce {
+    assert_ne!(n_threads, 0);
+    let mut prefix = md5::Md5::new();
+    prefix.update(key.trim().as_bytes());
+
+    let next_batch = AtomicU64::new(1);
+    let best = AtomicU64::new(Nonce::MAX);
+    std::thread::scope(|s| {
+        for _ in 0..n_threads {
+            s.spawn(|| loop {
+                let start = next_batch.fetch_add(BATCH, Ordering::Relaxed);
+                if start >= best.load(Ordering::Relaxed) {
+                    return;
+                }
+                let found = (start..start + BATCH).find(|nonce| {
+                    let mut md5 = prefix.clone();
+                    md5.update(nonce.to_string().as_bytes());
+                    has_leading_zeros(&md5.finish(), n_zeros)
+                });
+                if let Some(nonce) = found {
+                    best.fetch_min(nonce, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    best.into_inner()
+}
+
+fn n_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Leading zeros wanted by each part of the puzzle.
+const ZEROS: (usize, usize) = (5, 6);
+
+fn solve_both(input: &str, zeros: (usize, usize)) -> (Nonce, Nonce) {
+    let n_threads = n_threads();
+    let first = mine(input, zeros.0, n_threads);
+    let second = mine(input, zeros.1, n_threads);
+    (first, second)
+}
+
+#[test]
+fn example() {
+    assert_eq!(solve_both("abcdef", (4, ZEROS.0)), (31556, 609043));
+    assert_eq!(mine("pqrstuv\n", 5, n_threads()), 1048970);
+}
+
+#[test]
+fn any_zeros() {
+    let digest = md5::digest(b"abcdef609043");
+    assert!(has_leading_zeros(&digest, 5));
+    assert!(!has_leading_zeros(&digest, 6));
+    assert!(has_leading_zeros(&digest, 0));
+
+    for n_zeros in 0..=3 {
+        let expected = (1..)
+            .find(|n| has_leading_zeros(&md5::digest(format!("xyz{n}").as_bytes()), n_zeros))
+            .unwrap();
+        for n_threads in [1, 3] {
+            assert_eq!(mine("xyz", n_zeros, n_threads), expected);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+/// A single requirement a nice string has to satisfy.
+#[derive(Debug, PartialEq, Eq)]
+enum Rule {
+    /// At least that many vowels (`aeiou`)
+    Vowels(usize),
+    /// Some letter appears again after exactly `gap` other letters:
+    /// `0` is a double letter, `1` is `xyx`
+    Repeat { gap: usize },
+    /// None of the substrings occur
+    Forbid(Vec<String>),
+    /// Some pair of letters appears twice without overlapping
+    RepeatedPair,
+}
+
+fn to_key(a: u8, b: u8) -> u16 {
+    (u16::from(a) << u8::BITS) | u16::from(b)
+}
+
+fn has_repeated_pair(s: &[u8]) -> bool {
+    let mut positions = HashMap::with_capacity(s.len());
+    s.windows(2).enumerate().any(|(i, pair)| {
+        let &[a, b] = pair else { unreachable!() };
+        let first = *positions.entry(to_key(a, b)).or_insert(i);
+        i - first > 1
+    })
+}
+
+impl Rule {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut words = s.split_whitespace();
+        let name = words.next().ok_or("empty rule")?;
+        let mut number = || -> Result<usize, String> {
+            let n = words.next().ok_or(format!("`{name}` expects a number"))?;
+            n.parse()
+                .map_err(|_| format!("invalid number `{n}` for `{name}`"))
+        };
+        let rule = match name {
+            "vowels" => Self::Vowels(number()?),
+            "repeat" => {
+                // `matches` looks at windows of `gap + 2` letters
+                let gap = number()?;
+                if gap.checked_add(2).is_none() {
+                    return Err(format!("`repeat` gap {gap} is too large"));
+                }
+                Self::Repeat { gap }
+            }
+            "pair" => Self::RepeatedPair,
+            "forbid" => {
+                let substrings = words.by_ref().map(str::to_owned).collect::<Vec<_>>();
+                if substrings.is_empty() {
+                    return Err("`forbid` expects at least one substring".to_owned());
+                }
+                Self::Forbid(substrings)
+            }
+            _ => return Err(format!("unknown rule `{name}`")),
+        };
+        match words.next() {
+            None => Ok(rule),
+            Some(extra) => Err(format!("unexpected `{extra}` after `{name}`")),
+        }
+    }
+
+    fn matches(&self, s: &str) -> bool {
+        let bytes = s.as_bytes();
+        match self {
+            &Self::Vowels(n) => s.matches(&['a', 'e', 'i', 'o', 'u'][..]).count() >= n,
+            &Self::Repeat { gap } => bytes.windows(gap + 2).any(|w| w[0] == w[gap + 1]),
+            Self::Forbid(substrings) => substrings.iter().all(|sub| !s.contains(&sub[..])),
+            Self::RepeatedPair => has_repeated_pair(bytes),
+        }
+    }
+}
+
+/// A set of rules which all have to match. Written as rules separated by
+/// `;` or newlines, e.g. `vowels 3; repeat 0; forbid ab cd`.
+struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let rules = spec
+            .split(&[';', '\n'][..])
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(Rule::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    fn is_nice(&self, s: &str) -> bool {
+        self.rules.iter().all(|r| r.matches(s))
+    }
+
+    fn n_nice(&self, input: &str) -> usize {
+        input
+            .lines()
+            .filter(|l| !l.is_empty())
+            .filter(|l| self.is_nice(l))
+            .count()
+    }
+}
+
+const FIRST_POLICY: &str = "vowels 3; repeat 0; forbid ab cd pq xy";
+const SECOND_POLICY: &str = "pair; repeat 1";
+
+fn solve_both(input: &str) -> (usize, usize) {
+    let first = Policy::parse(FIRST_POLICY).unwrap().n_nice(input);
+    let second = Policy::parse(SECOND_POLICY).unwrap().n_nice(input);
+    (first, second)
+}
+
+#[test]
+fn examples() {
+    let first = Policy::parse(FIRST_POLICY).unwrap();
+    assert!(first.is_nice("ugknbfddgicrmopn"));
+    assert!(first.is_nice("aaa"));
+    assert!(!first.is_nice("jchzalrnumimnmhp"));
+    assert!(!first.is_nice("haegwjzuvuyypxyu"));
+    assert!(!first.is_nice("dvszwmarrgswjxmb"));
+
+    let second = Policy::parse(SECOND_POLICY).unwrap();
+    assert!(second.is_nice("qjhvhtzxzqqjkmpb"));
+    assert!(second.is_nice("xxyxx"));
+    assert!(!second.is_nice("uurcxstgmygtbstg"));
+    assert!(!second.is_nice("ieodomkazucvgmuy"));
+    assert!(!second.is_nice("aaa"));
+
+    assert_eq!(solve_both("ugknbfddgicrmopn\nxxyxx\naaa\n"), (2, 1));
+}
+
+#[test]
+fn policy_spec() {
+    let policy = Policy::parse("vowels 2\nforbid xx yy ; repeat 2").unwrap();
+    assert_eq!(
+        policy.rules,
+        [
+            Rule::Vowels(2),
+            Rule::Forbid(vec!["xx".into(), "yy".into()]),
+            Rule::Repeat { gap: 2 },
+        ]
+    );
+    assert_eq!(
+        Policy::parse("vowels three").err().unwrap(),
+        "invalid number `three` for `vowels`"
+    );
+    assert_eq!(
+        Policy::parse("pair 2").err().unwrap(),
+        "unexpected `2` after `pair`"
+    );
+    let too_large = format!("repeat {}", usize::MAX);
+    assert_eq!(
+        Policy::parse(&too_large).err().unwrap(),
+        format!("`repeat` gap {} is too large", usize::MAX)
+    );
+    assert_eq!(
+        Policy::parse("palindrome").err().unwrap(),
+        "unknown rule `palindrome`"
+    );
+}
@@ -1,8 +1,15 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 use std::ops::Range;
 
 type Dim = usize;
 const SIZE: Dim = 1000;
-type Grid<T> = [[T; SIZE]; SIZE];
+
+#[derive(Clone, Copy)]
+struct Dims {
+    rows: Dim,
+    cols: Dim,
+}
 
 enum Action {
     On,
@@ -52,19 +59,54 @@ impl Apply<Brightness> for Action {
     }
 }
 
+/// Numeric value of a light, used by the region queries and image export.
+trait Level {
+    fn level(&self) -> u64;
+}
+
+impl Level for bool {
+    fn level(&self) -> u64 {
+        *self as u64
+    }
+}
+
+impl Level for Brightness {
+    fn level(&self) -> u64 {
+        u64::from(*self)
+    }
+}
+
 fn parse_pair(s: &str) -> (Dim, Dim) {
     let (first, second) = s.split_once(",").unwrap();
     (first.parse().unwrap(), second.parse().unwrap())
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
 struct Rect {
     row_range: Range<Dim>,
     col_range: Range<Dim>,
 }
 
 impl Rect {
-    fn contains(&self, (row, col): (Dim, Dim)) -> bool {
-        self.row_range.contains(&row) && self.col_range.contains(&col)
+    fn area(&self) -> u64 {
+        (self.row_range.len() as u64) * (self.col_range.len() as u64)
+    }
+
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        let overlap = |a: &Range<Dim>, b: &Range<Dim>| a.start.max(b.start)..a.end.min(b.end);
+        let ret = Self {
+            row_range: overlap(&self.row_range, &other.row_range),
+            col_range: overlap(&self.col_range, &other.col_range),
+        };
+        (!ret.row_range.is_empty() && !ret.col_range.is_empty()).then_some(ret)
+    }
+
+    fn hull(&self, other: &Self) -> Self {
+        let join = |a: &Range<Dim>, b: &Range<Dim>| a.start.min(b.start)..a.end.max(b.end);
+        Self {
+            row_range: join(&self.row_range, &other.row_range),
+            col_range: join(&self.col_range, &other.col_range),
+        }
     }
 }
 
@@ -93,42 +135,275 @@ fn parse_commands(s: &str) -> Vec<Command> {
     s.lines().filter(|l| !l.is_empty()).map(Command::parse).collect()
 }
 
-// fn solve(input: &str) -> Dim {
-//     let commands = parse_commands(input);
-//     let mut grid = [[false; SIZE]; SIZE];
-    
-//     grid.iter().flat_map(|row| row.iter()).filter(|&&is_lit| is_lit as Dim).sum()
-// }
+/// Sorted distinct coordinates where some rectangle starts or ends, always
+/// including `0` and `size`. Neighbouring bounds delimit stripes of cells
+/// which no command tells apart.
+fn bounds(size: Dim, ranges: impl Iterator<Item = Range<Dim>>) -> Vec<Dim> {
+    let mut ret = ranges
+        .flat_map(|r| {
+            assert!(r.end <= size, "range {r:?} is out of grid bounds");
+            [r.start, r.end]
+        })
+        .chain([0, size])
+        .collect::<Vec<_>>();
+    ret.sort_unstable();
+    ret.dedup();
+    ret
+}
 
-fn apply_to_grid<T>(grid: &mut Grid<T>, commands: &[Command])
-where
-    Action: Apply<T>,
-{
-    for (irow, row) in grid.iter_mut().enumerate() {
-        for (icol, val) in row.iter_mut().enumerate() {
-            for c in commands {
-                if c.area.contains((irow, icol)) {
-                    c.action.apply(val);
-                }
+fn stripes(bounds: &[Dim], range: &Range<Dim>) -> Range<usize> {
+    let start = bounds.binary_search(&range.start).unwrap();
+    let end = bounds.binary_search(&range.end).unwrap();
+    start..end
+}
+
+/// Grid compressed along the rectangle edges: every block holds the state
+/// shared by all cells in it, so the work depends on the number of commands
+/// and not on the grid area.
+struct Grid<T> {
+    row_bounds: Vec<Dim>,
+    col_bounds: Vec<Dim>,
+    blocks: Vec<T>,
+}
+
+impl<T: Clone + Default> Grid<T> {
+    fn new(dims: Dims, commands: &[Command]) -> Self {
+        let row_bounds = bounds(dims.rows, commands.iter().map(|c| c.area.row_range.clone()));
+        let col_bounds = bounds(dims.cols, commands.iter().map(|c| c.area.col_range.clone()));
+        let n_blocks = (row_bounds.len() - 1) * (col_bounds.len() - 1);
+        Self {
+            row_bounds,
+            col_bounds,
+            blocks: vec![T::default(); n_blocks],
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    fn n_cols(&self) -> usize {
+        self.col_bounds.len() - 1
+    }
+
+    fn apply(&mut self, commands: &[Command])
+    where
+        Action: Apply<T>,
+    {
+        let n_cols = self.n_cols();
+        for c in commands {
+            let cols = stripes(&self.col_bounds, &c.area.col_range);
+            for irow in stripes(&self.row_bounds, &c.area.row_range) {
+                let row = &mut self.blocks[irow * n_cols..][..n_cols];
+                row[cols.clone()]
+                    .iter_mut()
+                    .for_each(|val| c.action.apply(val));
             }
         }
     }
+
+    fn blocks(&self) -> impl Iterator<Item = (Rect, &T)> + '_ {
+        let n_cols = self.n_cols();
+        self.blocks.iter().enumerate().map(move |(i, val)| {
+            let (irow, icol) = (i / n_cols, i % n_cols);
+            let area = Rect {
+                row_range: self.row_bounds[irow]..self.row_bounds[irow + 1],
+                col_range: self.col_bounds[icol]..self.col_bounds[icol + 1],
+            };
+            (area, val)
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ImageFormat {
+    /// Greyscale, `P5`.
+    Pgm,
+    /// RGB with equal channels, `P6`.
+    Ppm,
 }
 
-fn solve_both(input: &str) -> (usize, Brightness) {
+impl<T: Level> Grid<T> {
+    fn dims(&self) -> Dims {
+        Dims {
+            rows: *self.row_bounds.last().unwrap(),
+            cols: *self.col_bounds.last().unwrap(),
+        }
+    }
+
+    /// Lit count for `bool` lights, total brightness for `Brightness` ones.
+    fn sum(&self, area: &Rect) -> u64 {
+        self.blocks()
+            .filter_map(|(block, val)| Some(block.intersect(area)?.area() * val.level()))
+            .sum()
+    }
+
+    fn total(&self) -> u64 {
+        self.blocks()
+            .map(|(block, val)| block.area() * val.level())
+            .sum()
+    }
+
+    /// Number of cells for every level present in the grid.
+    fn histogram(&self) -> BTreeMap<u64, u64> {
+        let mut ret = BTreeMap::new();
+        for (block, val) in self.blocks() {
+            *ret.entry(val.level()).or_insert(0) += block.area();
+        }
+        ret
+    }
+
+    /// Smallest rectangle containing every cell with a non-zero level.
+    fn bounding_box(&self) -> Option<Rect> {
+        self.blocks()
+            .filter(|(_, val)| val.level() != 0)
+            .map(|(block, _)| block)
+            .reduce(|acc, block| acc.hull(&block))
+    }
+
+    /// Writes the grid as a binary netpbm image, one pixel per cell, with
+    /// levels scaled so that the brightest cell is white.
+    fn write_image(&self, out: &mut impl Write, format: ImageFormat) -> io::Result<()> {
+        let Dims { rows, cols } = self.dims();
+        let max = self.blocks().map(|(_, val)| val.level()).max().unwrap_or(0);
+        let (magic, channels) = match format {
+            ImageFormat::Pgm => ("P5", 1),
+            ImageFormat::Ppm => ("P6", 3),
+        };
+        write!(out, "{magic}\n{cols} {rows}\n255\n")?;
+
+        let n_cols = self.n_cols();
+        let mut line = Vec::with_capacity(cols * channels);
+        for (irow, row) in self.blocks.chunks(n_cols).enumerate() {
+            line.clear();
+            for (icol, val) in row.iter().enumerate() {
+                let grey = (val.level() * 255).checked_div(max).unwrap_or(0) as u8;
+                let width = self.col_bounds[icol + 1] - self.col_bounds[icol];
+                line.extend(std::iter::repeat_n(grey, width * channels));
+            }
+            for _ in self.row_bounds[irow]..self.row_bounds[irow + 1] {
+                out.write_all(&line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_to_grid<T: Clone + Default>(dims: Dims, commands: &[Command]) -> Grid<T>
+where
+    Action: Apply<T>,
+{
+    let mut grid = Grid::new(dims, commands);
+    grid.apply(commands);
+    grid
+}
+
+fn solve_both_sized(input: &str, dims: Dims) -> (u64, u64) {
     let commands = parse_commands(input);
 
-    let first = {
-        let mut grid = [[false; SIZE]; SIZE];
-        apply_to_grid(&mut grid, &commands);
-        grid.iter().flat_map(|row| row.iter()).map(|&lit| lit as usize).sum()
+    let first = apply_to_grid::<bool>(dims, &commands).total();
+    let second = apply_to_grid::<Brightness>(dims, &commands).total();
+
+    (first, second)
+}
+
+fn solve_both(input: &str) -> (u64, u64) {
+    let dims = Dims {
+        rows: SIZE,
+        cols: SIZE,
     };
-    
-    let second = {
-        let mut grid = [[0; SIZE]; SIZE];
-        apply_to_grid(&mut grid, &commands);
-        grid.iter().flat_map(|row| row.iter()).sum()
+    solve_both_sized(input, dims)
+}
+
+#[test]
+fn example() {
+    const INPUT: &str = "
+turn on 0,0 through 999,999
+toggle 0,0 through 999,0
+turn off 499,499 through 500,500
+";
+    assert_eq!(solve_both(INPUT).0, 1_000_000 - 1000 - 4);
+    assert_eq!(solve_both("toggle 0,0 through 999,999").1, 2_000_000);
+
+    let huge = Dims {
+        rows: 100_000,
+        cols: 100_000,
     };
-    
-    (first, second)
+    let input = "turn on 0,0 through 99999,99999\ntoggle 1,1 through 99998,99998";
+    assert_eq!(
+        solve_both_sized(input, huge),
+        (4 * 99_999, 10_000_000_000 + 2 * 99_998 * 99_998)
+    );
+}
+
+#[test]
+fn matches_brute_force() {
+    const INPUT: &str = "
+turn on 1,2 through 6,4
+toggle 0,0 through 3,7
+turn off 2,2 through 2,9
+toggle 5,1 through 9,9
+turn on 4,4 through 4,4
+turn off 0,7 through 9,7
+";
+    let dims = Dims { rows: 10, cols: 10 };
+    let commands = parse_commands(INPUT);
+    let mut lit = 0;
+    let mut brightness = 0;
+    for row in 0..dims.rows {
+        for col in 0..dims.cols {
+            let mut cell = (false, 0 as Brightness);
+            for c in commands
+                .iter()
+                .filter(|c| c.area.row_range.contains(&row) && c.area.col_range.contains(&col))
+            {
+                c.action.apply(&mut cell.0);
+                c.action.apply(&mut cell.1);
+            }
+            lit += cell.0 as u64;
+            brightness += u64::from(cell.1);
+        }
+    }
+    assert_eq!(solve_both_sized(INPUT, dims), (lit, brightness));
+}
+
+#[test]
+fn queries() {
+    const INPUT: &str = "
+turn on 2,1 through 4,3
+toggle 3,2 through 5,5
+turn off 0,0 through 2,1
+";
+    let dims = Dims { rows: 8, cols: 6 };
+    let commands = parse_commands(INPUT);
+    let rect = |rows: Range<Dim>, cols: Range<Dim>| Rect {
+        row_range: rows,
+        col_range: cols,
+    };
+
+    let lit = apply_to_grid::<bool>(dims, &commands);
+    assert_eq!(lit.total(), 12);
+    assert_eq!(lit.sum(&rect(3..5, 2..4)), 0);
+    assert_eq!(lit.sum(&rect(0..4, 0..4)), 3);
+    assert_eq!(lit.histogram(), BTreeMap::from([(0, 36), (1, 12)]));
+    assert_eq!(lit.bounding_box(), Some(rect(2..6, 1..6)));
+
+    let bright = apply_to_grid::<Brightness>(dims, &commands);
+    assert_eq!(bright.sum(&rect(3..5, 2..4)), 12);
+    assert_eq!(
+        bright.histogram(),
+        BTreeMap::from([(0, 32), (1, 4), (2, 8), (3, 4)])
+    );
+    assert_eq!(bright.bounding_box(), Some(rect(2..6, 1..6)));
+
+    let mut pgm = Vec::new();
+    bright.write_image(&mut pgm, ImageFormat::Pgm).unwrap();
+    let (header, pixels) = pgm.split_at(b"P5\n6 8\n255\n".len());
+    assert_eq!(header, b"P5\n6 8\n255\n");
+    assert_eq!(pixels.len(), 48);
+    assert_eq!(&pixels[3 * 6..4 * 6], [0, 85, 255, 255, 170, 170]);
+
+    let mut ppm = Vec::new();
+    apply_to_grid::<Brightness>(Dims { rows: 1, cols: 1 }, &[])
+        .write_image(&mut ppm, ImageFormat::Ppm)
+        .unwrap();
+    assert_eq!(ppm, b"P6\n1 1\n255\n\0\0\0");
 }
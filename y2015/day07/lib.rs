@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::fmt::Write;
 
+// Wires are 16 bits wide and shifting bits out is part of the puzzle, so
+// unlike other days there is no checked or wide mode for signals
 type Signal = u16;
 type Shift = u8;
 
@@ -15,15 +18,30 @@ impl<'a> From<&'a str> for Input<&'a str> {
     }
 }
 
+impl<Idx> Input<Idx> {
+    fn map<T>(self, f: &mut impl FnMut(Idx) -> T) -> Input<T> {
+        match self {
+            Self::Wire(w) => Input::Wire(f(w)),
+            Self::Signal(s) => Input::Signal(s),
+        }
+    }
+
+    fn wire(&self) -> Option<&Idx> {
+        match self {
+            Self::Wire(w) => Some(w),
+            Self::Signal(_) => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Gate<Idx> {
-    Wire(Idx),
-    Constant(Signal),
-    And(Input<Idx>, Idx),
-    Or(Idx, Idx),
-    LeftShift(Idx, Shift),
-    RightShift(Idx, Shift),
-    Not(Idx),
+    Wire(Input<Idx>),
+    And(Input<Idx>, Input<Idx>),
+    Or(Input<Idx>, Input<Idx>),
+    LeftShift(Input<Idx>, Shift),
+    RightShift(Input<Idx>, Shift),
+    Not(Input<Idx>),
 }
 
 impl<'a> Gate<&'a str> {
@@ -33,59 +51,290 @@ impl<'a> Gate<&'a str> {
     }
 
     fn parse_inner(gate: &'a str) -> Self {
-        if let Ok(value) = gate.parse() {
-            return Self::Constant(value);
-        }
         if let Some(input) = gate.strip_prefix("NOT ") {
-            return Self::Not(input);
+            return Self::Not(input.into());
         }
         if let Some((a, b)) = gate.split_once(" AND ") {
-            return Self::And(a.into(), b);
+            return Self::And(a.into(), b.into());
         }
         if let Some((a, b)) = gate.split_once(" OR ") {
-            return Self::Or(a, b);
+            return Self::Or(a.into(), b.into());
         }
         if let Some((a, b)) = gate.split_once(" LSHIFT ") {
-            return Self::LeftShift(a, b.parse().unwrap());
+            return Self::LeftShift(a.into(), b.parse().unwrap());
         }
         if let Some((a, b)) = gate.split_once(" RSHIFT ") {
-            return Self::RightShift(a, b.parse().unwrap());
+            return Self::RightShift(a.into(), b.parse().unwrap());
+        }
+        Self::Wire(gate.into())
+    }
+}
+
+impl<Idx> Gate<Idx> {
+    fn map<T>(self, mut f: impl FnMut(Idx) -> T) -> Gate<T> {
+        match self {
+            Self::Wire(a) => Gate::Wire(a.map(&mut f)),
+            Self::And(a, b) => Gate::And(a.map(&mut f), b.map(&mut f)),
+            Self::Or(a, b) => Gate::Or(a.map(&mut f), b.map(&mut f)),
+            Self::LeftShift(a, shift) => Gate::LeftShift(a.map(&mut f), shift),
+            Self::RightShift(a, shift) => Gate::RightShift(a.map(&mut f), shift),
+            Self::Not(a) => Gate::Not(a.map(&mut f)),
+        }
+    }
+
+    fn inputs(&self) -> impl Iterator<Item = &Idx> {
+        let (a, b) = match self {
+            Self::And(a, b) | Self::Or(a, b) => (a, Some(b)),
+            Self::Wire(a) | Self::LeftShift(a, _) | Self::RightShift(a, _) | Self::Not(a) => {
+                (a, None)
+            }
+        };
+        a.wire().into_iter().chain(b.and_then(Input::wire))
+    }
+}
+
+impl Gate<usize> {
+    fn eval(&self, values: &[Signal]) -> Signal {
+        let get = |input: Input<usize>| match input {
+            Input::Wire(w) => values[w],
+            Input::Signal(s) => s,
+        };
+        match *self {
+            Self::Wire(a) => get(a),
+            Self::And(a, b) => get(a) & get(b),
+            Self::Or(a, b) => get(a) | get(b),
+            Self::LeftShift(a, shift) => get(a)
+                .checked_shl(shift.into())
+                .expect("shift amount exceeds signal width"),
+            Self::RightShift(a, shift) => get(a)
+                .checked_shr(shift.into())
+                .expect("shift amount exceeds signal width"),
+            Self::Not(a) => !get(a),
         }
-        Self::Wire(gate)
     }
 }
 
-fn eval<'a>(
-    wire: &'a str,
-    gates: &HashMap<&'a str, Gate<&'a str>>,
-    values: &mut HashMap<&'a str, Signal>,
-) -> Signal {
-    if let Some(&answer) = values.get(&wire) {
-        return answer;
+#[derive(Debug, PartialEq, Eq)]
+enum CircuitError<'a> {
+    /// The wire is used as an input but nothing drives it.
+    Undriven(&'a str),
+    /// The wires form a loop, each one feeding the next and the last one
+    /// feeding the first.
+    Cycle(Vec<&'a str>),
+}
+
+/// Gates compiled into a tape ordered so that every gate comes after the
+/// gates driving its inputs, so the whole circuit is evaluated in one pass.
+struct Circuit<'a> {
+    names: Vec<&'a str>,
+    index: HashMap<&'a str, usize>,
+    /// Gate driving each wire.
+    gates: Vec<Gate<usize>>,
+    /// Wires in evaluation order.
+    tape: Vec<usize>,
+}
+
+impl<'a> Circuit<'a> {
+    fn compile(gates: HashMap<&'a str, Gate<&'a str>>) -> Result<Self, CircuitError<'a>> {
+        let names = gates.keys().copied().collect::<Vec<_>>();
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (name, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut drivers = vec![None; names.len()];
+        for (output, gate) in gates {
+            if let Some(&w) = gate.inputs().find(|w| !index.contains_key(*w)) {
+                return Err(CircuitError::Undriven(w));
+            }
+            drivers[index[output]] = Some(gate.map(|w| index[w]));
+        }
+        let gates = drivers.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+
+        // Kahn's algorithm over "input feeds output" edges
+        let mut n_pending = vec![0; names.len()];
+        let mut fanout = vec![Vec::new(); names.len()];
+        for (output, gate) in gates.iter().enumerate() {
+            for &input in gate.inputs() {
+                n_pending[output] += 1;
+                fanout[input].push(output);
+            }
+        }
+        let mut ready = (0..names.len())
+            .filter(|&w| n_pending[w] == 0)
+            .collect::<Vec<_>>();
+        let mut tape = Vec::with_capacity(names.len());
+        while let Some(wire) = ready.pop() {
+            tape.push(wire);
+            for &output in &fanout[wire] {
+                n_pending[output] -= 1;
+                if n_pending[output] == 0 {
+                    ready.push(output);
+                }
+            }
+        }
+
+        if tape.len() < names.len() {
+            let cycle = find_cycle(&gates, &n_pending);
+            return Err(CircuitError::Cycle(
+                cycle.into_iter().map(|w| names[w]).collect(),
+            ));
+        }
+
+        Ok(Self {
+            names,
+            index,
+            gates,
+            tape,
+        })
     }
 
-    let answer = match gates[&wire] {
-        Gate::Wire(w) => eval(w, gates, values),
-        Gate::Constant(v) => v,
-        Gate::And(a, b) => {
-            let left = match a {
-                Input::Wire(w) => eval(w, gates, values),
-                Input::Signal(s) => s,
+    fn wire(&self, name: &str) -> usize {
+        *self
+            .index
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown wire {name}"))
+    }
+
+    fn run(&self, overrides: &HashMap<&str, Signal>, needed: Option<&[bool]>) -> Vec<Signal> {
+        let mut values = vec![0; self.names.len()];
+        let mut overridden = vec![false; self.names.len()];
+        for (&name, &value) in overrides {
+            let wire = self.wire(name);
+            values[wire] = value;
+            overridden[wire] = true;
+        }
+        for &wire in &self.tape {
+            if needed.is_none_or(|needed| needed[wire]) && !overridden[wire] {
+                values[wire] = self.gates[wire].eval(&values);
+            }
+        }
+        values
+    }
+
+    /// Signals on every wire, with overridden wires cut off from their gates.
+    fn eval_all(&self, overrides: &HashMap<&str, Signal>) -> HashMap<&'a str, Signal> {
+        let values = self.run(overrides, None);
+        self.names.iter().copied().zip(values).collect()
+    }
+
+    /// Signals on `wires` only, evaluating just the gates they depend on.
+    fn eval(&self, wires: &[&str], overrides: &HashMap<&str, Signal>) -> Vec<Signal> {
+        let mut needed = vec![false; self.names.len()];
+        let mut stack = wires.iter().map(|w| self.wire(w)).collect::<Vec<_>>();
+        while let Some(wire) = stack.pop() {
+            if std::mem::replace(&mut needed[wire], true)
+                || overrides.contains_key(self.names[wire])
+            {
+                continue;
+            }
+            stack.extend(self.gates[wire].inputs().copied());
+        }
+
+        let values = self.run(overrides, Some(&needed));
+        wires.iter().map(|w| values[self.wire(w)]).collect()
+    }
+
+    fn sorted_wires(&self) -> Vec<usize> {
+        let mut ret = (0..self.names.len()).collect::<Vec<_>>();
+        ret.sort_unstable_by_key(|&w| self.names[w]);
+        ret
+    }
+
+    /// Graphviz graph with wires as ellipses and gates as boxes between them.
+    /// Plain `x -> y` connections are drawn as a single edge.
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph circuit {\n");
+        for wire in self.sorted_wires() {
+            let name = self.names[wire];
+            writeln!(out, "    w_{name} [label=\"{name}\"];").unwrap();
+            let (op, inputs) = match self.gates[wire] {
+                Gate::Wire(a) => {
+                    let from = self.dot_input(&mut out, a, name, 0);
+                    writeln!(out, "    {from} -> w_{name};").unwrap();
+                    continue;
+                }
+                Gate::And(a, b) => ("AND".to_owned(), vec![a, b]),
+                Gate::Or(a, b) => ("OR".to_owned(), vec![a, b]),
+                Gate::LeftShift(a, shift) => (format!("LSHIFT {shift}"), vec![a]),
+                Gate::RightShift(a, shift) => (format!("RSHIFT {shift}"), vec![a]),
+                Gate::Not(a) => ("NOT".to_owned(), vec![a]),
             };
-            left & eval(b, gates, values)
+            writeln!(out, "    g_{name} [label=\"{op}\", shape=box];").unwrap();
+            for (slot, input) in inputs.into_iter().enumerate() {
+                let from = self.dot_input(&mut out, input, name, slot);
+                writeln!(out, "    {from} -> g_{name};").unwrap();
+            }
+            writeln!(out, "    g_{name} -> w_{name};").unwrap();
         }
-        Gate::Or(a, b) => eval(a, gates, values) | eval(b, gates, values),
-        Gate::LeftShift(wire, shift) => {
-            eval(wire, gates, values).checked_shl(shift.into()).unwrap()
+        out.push_str("}\n");
+        out
+    }
+
+    /// Node id for a gate input, declaring a node for constants.
+    fn dot_input(&self, out: &mut String, input: Input<usize>, gate: &str, slot: usize) -> String {
+        match input {
+            Input::Wire(w) => format!("w_{}", self.names[w]),
+            Input::Signal(s) => {
+                let id = format!("k_{gate}_{slot}");
+                writeln!(out, "    {id} [label=\"{s}\", shape=plaintext];").unwrap();
+                id
+            }
         }
-        Gate::RightShift(wire, shift) => {
-            eval(wire, gates, values).checked_shr(shift.into()).unwrap()
+    }
+
+    /// Structural Verilog module with `output` as its only port. Wire names
+    /// get a `w_` prefix since some of them (`or`, `if`, ...) are keywords.
+    fn to_verilog(&self, module: &str, output: &str) -> String {
+        let input = |input: Input<usize>| match input {
+            Input::Wire(w) => format!("w_{}", self.names[w]),
+            Input::Signal(s) => format!("16'd{s}"),
+        };
+
+        let mut out = String::new();
+        writeln!(out, "module {module}(output wire [15:0] {output});").unwrap();
+        let wires = self.sorted_wires();
+        for &wire in &wires {
+            writeln!(out, "    wire [15:0] w_{};", self.names[wire]).unwrap();
         }
-        Gate::Not(wire) => !eval(wire, gates, values),
-    };
+        for &wire in &wires {
+            let expr = match self.gates[wire] {
+                Gate::Wire(a) => input(a),
+                Gate::And(a, b) => format!("{} & {}", input(a), input(b)),
+                Gate::Or(a, b) => format!("{} | {}", input(a), input(b)),
+                Gate::LeftShift(a, shift) => format!("{} << {shift}", input(a)),
+                Gate::RightShift(a, shift) => format!("{} >> {shift}", input(a)),
+                Gate::Not(a) => format!("~{}", input(a)),
+            };
+            writeln!(out, "    assign w_{} = {expr};", self.names[wire]).unwrap();
+        }
+        writeln!(
+            out,
+            "    assign {output} = w_{};",
+            self.names[self.wire(output)]
+        )
+        .unwrap();
+        out.push_str("endmodule\n");
+        out
+    }
+}
 
-    values.insert(wire, answer);
-    answer
+/// Every wire left with pending inputs after the sort has at least one input
+/// which is also pending, so following those inputs must end up in a loop.
+fn find_cycle(gates: &[Gate<usize>], n_pending: &[usize]) -> Vec<usize> {
+    let mut wire = (0..gates.len()).find(|&w| n_pending[w] > 0).unwrap();
+    let mut seen_at = HashMap::new();
+    let mut path = Vec::new();
+    while !seen_at.contains_key(&wire) {
+        seen_at.insert(wire, path.len());
+        path.push(wire);
+        wire = *gates[wire].inputs().find(|&&w| n_pending[w] > 0).unwrap();
+    }
+    let mut cycle = path.split_off(seen_at[&wire]);
+    // path follows inputs, report the loop in signal flow order
+    cycle.reverse();
+    cycle
 }
 
 const ANSWER_WIRE: &str = "a";
@@ -100,10 +349,134 @@ fn parse_gates(s: &str) -> HashMap<&str, Gate<&str>> {
 }
 
 fn solve_both(input: &str) -> (Signal, Signal) {
-    let gates = parse_gates(input);
-    let mut values = HashMap::with_capacity(gates.len());
-    let override_ = eval(ANSWER_WIRE, &gates, &mut values);
-    values.clear();
-    values.insert(OVERRIDE_WIRE, override_);
-    (override_, eval(ANSWER_WIRE, &gates, &mut values))
+    let circuit = Circuit::compile(parse_gates(input)).unwrap();
+    let [first] = circuit.eval(&[ANSWER_WIRE], &HashMap::new())[..] else {
+        unreachable!()
+    };
+    let overrides = HashMap::from([(OVERRIDE_WIRE, first)]);
+    let [second] = circuit.eval(&[ANSWER_WIRE], &overrides)[..] else {
+        unreachable!()
+    };
+    (first, second)
+}
+
+#[test]
+fn example() {
+    const INPUT: &str = "
+123 -> x
+456 -> y
+x AND y -> d
+x OR y -> e
+x LSHIFT 2 -> f
+y RSHIFT 2 -> g
+NOT x -> h
+NOT y -> i
+";
+    let circuit = Circuit::compile(parse_gates(INPUT)).unwrap();
+    let values = circuit.eval_all(&HashMap::new());
+    let expected = [
+        ("d", 72),
+        ("e", 507),
+        ("f", 492),
+        ("g", 114),
+        ("h", 65412),
+        ("i", 65079),
+        ("x", 123),
+        ("y", 456),
+    ];
+    assert_eq!(values, HashMap::from(expected));
+
+    let overrides = HashMap::from([("x", 0xff00)]);
+    assert_eq!(
+        circuit.eval(&["d", "h", "y"], &overrides),
+        [0x0100, 0x00ff, 456]
+    );
+
+    // long chains are evaluated without recursion
+    let chain = (0..100_000)
+        .map(|i| format!("w{i} -> w{}\n", i + 1))
+        .chain(["1 AND 3 -> w0".to_owned()])
+        .collect::<String>();
+    let circuit = Circuit::compile(parse_gates(&chain)).unwrap();
+    assert_eq!(circuit.eval(&["w100000"], &HashMap::new()), [1]);
+}
+
+#[test]
+fn override_wire() {
+    const INPUT: &str = "
+123 -> x
+456 -> y
+x AND y -> d
+d OR b -> e
+e RSHIFT 1 -> a
+3 -> b
+";
+    // a = (72 | 3) >> 1, then again with b overridden by that
+    assert_eq!(solve_both(INPUT), (37, 54));
+}
+
+#[test]
+fn errors() {
+    let input = "1 -> a\na AND d -> b\nb OR c -> c";
+    assert_eq!(
+        Circuit::compile(parse_gates(input)).err(),
+        Some(CircuitError::Undriven("d"))
+    );
+
+    let input = "1 -> a\na AND d -> b\nNOT b -> c\nc LSHIFT 1 -> d\nd -> e";
+    let Some(CircuitError::Cycle(mut cycle)) = Circuit::compile(parse_gates(input)).err() else {
+        panic!("cycle not detected")
+    };
+    let start = cycle.iter().position(|&w| w == "b").unwrap();
+    cycle.rotate_left(start);
+    assert_eq!(cycle, ["b", "c", "d"]);
+}
+
+#[test]
+fn export() {
+    const INPUT: &str = "
+123 -> x
+x AND 7 -> a
+NOT x -> or
+or LSHIFT 2 -> b
+";
+    let circuit = Circuit::compile(parse_gates(INPUT)).unwrap();
+    assert_eq!(
+        circuit.to_dot(),
+        r#"digraph circuit {
+    w_a [label="a"];
+    g_a [label="AND", shape=box];
+    w_x -> g_a;
+    k_a_1 [label="7", shape=plaintext];
+    k_a_1 -> g_a;
+    g_a -> w_a;
+    w_b [label="b"];
+    g_b [label="LSHIFT 2", shape=box];
+    w_or -> g_b;
+    g_b -> w_b;
+    w_or [label="or"];
+    g_or [label="NOT", shape=box];
+    w_x -> g_or;
+    g_or -> w_or;
+    w_x [label="x"];
+    k_x_0 [label="123", shape=plaintext];
+    k_x_0 -> w_x;
+}
+"#
+    );
+    assert_eq!(
+        circuit.to_verilog("circuit", ANSWER_WIRE),
+        "module circuit(output wire [15:0] a);
+    wire [15:0] w_a;
+    wire [15:0] w_b;
+    wire [15:0] w_or;
+    wire [15:0] w_x;
+    assign w_a = w_x & 16'd7;
+    assign w_b = w_or << 2;
+    assign w_or = ~w_x;
+    assign w_x = 16'd123;
+    assign a = w_a;
+endmodule
+"
+    );
 }
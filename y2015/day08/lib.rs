@@ -1,33 +1,104 @@
-fn escape_excess(s: &str) -> usize {
-    let mut ret = 0;
-    let mut iter = s.as_bytes().iter().copied();
-    while let Some(ch) = iter.next() {
-        if ch != b'\\' {
-            continue;
-        }
-        match iter.next().unwrap() {
-            b'\\' => ret += 1,
-            b'"' => ret += 1,
-            b'x' => {
-                ret += 3;
-                iter.next().unwrap();
-                iter.next().unwrap();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrorKind {
+    MissingOpenQuote,
+    MissingCloseQuote,
+    /// A `"` inside the literal which is not escaped.
+    StrayQuote,
+    UnknownEscape(u8),
+    TruncatedEscape,
+    InvalidHexDigit(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DecodeError {
+    /// Byte offset of the offending character in the literal.
+    pos: usize,
+    kind: ErrorKind,
+}
+
+fn hex_digit(pos: usize, ch: Option<&u8>) -> Result<u8, DecodeError> {
+    let err = |kind| DecodeError { pos, kind };
+    let &ch = ch.ok_or(err(ErrorKind::TruncatedEscape))?;
+    (ch as char)
+        .to_digit(16)
+        .map(|d| d as u8)
+        .ok_or(err(ErrorKind::InvalidHexDigit(ch)))
+}
+
+/// Turns a quoted literal into the bytes it stands for.
+fn decode(literal: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let err = |pos, kind| Err(DecodeError { pos, kind });
+    if literal.first() != Some(&b'"') {
+        return err(0, ErrorKind::MissingOpenQuote);
+    }
+
+    let mut ret = Vec::with_capacity(literal.len());
+    let mut pos = 1;
+    loop {
+        match literal.get(pos) {
+            None => return err(pos, ErrorKind::MissingCloseQuote),
+            Some(b'"') if pos + 1 == literal.len() => return Ok(ret),
+            Some(b'"') => return err(pos, ErrorKind::StrayQuote),
+            Some(b'\\') => match literal.get(pos + 1) {
+                Some(&ch @ (b'\\' | b'"')) => {
+                    ret.push(ch);
+                    pos += 2;
+                }
+                Some(b'x') => {
+                    let high = hex_digit(pos + 2, literal.get(pos + 2))?;
+                    let low = hex_digit(pos + 3, literal.get(pos + 3))?;
+                    ret.push(high << 4 | low);
+                    pos += 4;
+                }
+                Some(&ch) => return err(pos + 1, ErrorKind::UnknownEscape(ch)),
+                None => return err(pos + 1, ErrorKind::TruncatedEscape),
+            },
+            Some(&ch) => {
+                ret.push(ch);
+                pos += 1;
             }
-            _ => unreachable!(),
         }
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Escapes {
+    /// `\` and `"` by name, anything outside printable ASCII as `\xHH`.
+    All,
+    /// Only `\` and `"`, as the puzzle does; other bytes go through as they
+    /// are.
+    Quotes,
+}
+
+/// Quotes `bytes` so that `decode` gives them back.
+fn encode(bytes: &[u8], escapes: Escapes) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(bytes.len() + 2);
+    ret.push(b'"');
+    for &ch in bytes {
+        match ch {
+            b'\\' | b'"' => ret.extend([b'\\', ch]),
+            b' '..=b'~' => ret.push(ch),
+            _ if escapes == Escapes::Quotes => ret.push(ch),
+            _ => ret.extend(format!("\\x{ch:02x}").bytes()),
+        }
+    }
+    ret.push(b'"');
     ret
 }
 
-fn count_escape_additional(s: &str) -> usize {
-    s.matches(&['\\', '"'][..]).count()
+fn escape_excess(literal: &str) -> usize {
+    literal.len() - decode(literal.as_bytes()).unwrap().len()
+}
+
+fn count_escape_additional(literal: &str) -> usize {
+    encode(literal.as_bytes(), Escapes::Quotes).len() - literal.len()
 }
 
 fn solve_first(input: &str) -> usize {
     input
         .lines()
         .filter(|l| !l.is_empty())
-        .map(|l| escape_excess(&l[1..l.len() - 1]) + 2)
+        .map(escape_excess)
         .sum()
 }
 
@@ -35,7 +106,7 @@ fn solve_second(input: &str) -> usize {
     input
         .lines()
         .filter(|l| !l.is_empty())
-        .map(|l| count_escape_additional(l) + 2)
+        .map(count_escape_additional)
         .sum()
 }
 
@@ -43,6 +114,54 @@ fn solve_second(input: &str) -> usize {
 fn example() {
     assert_eq!(solve_first(INPUT), 12);
     assert_eq!(solve_second(INPUT), 19);
+    assert_eq!(count_escape_additional("é"), 2);
+    assert_eq!(count_escape_additional("\t\\\""), 4);
+}
+
+#[test]
+fn malformed() {
+    let decode = |s: &str| decode(s.as_bytes()).map_err(|e| (e.pos, e.kind));
+    assert_eq!(decode(r#"abc""#), Err((0, ErrorKind::MissingOpenQuote)));
+    assert_eq!(decode(r#""abc"#), Err((4, ErrorKind::MissingCloseQuote)));
+    assert_eq!(decode(r#""a\""#), Err((4, ErrorKind::MissingCloseQuote)));
+    assert_eq!(decode(r#""a"b""#), Err((2, ErrorKind::StrayQuote)));
+    assert_eq!(decode(r#""a\n""#), Err((3, ErrorKind::UnknownEscape(b'n'))));
+    assert_eq!(
+        decode(r#""\x4g""#),
+        Err((4, ErrorKind::InvalidHexDigit(b'g')))
+    );
+    assert_eq!(decode(r#""\x4"#), Err((4, ErrorKind::TruncatedEscape)));
+    assert_eq!(decode(r#""\"#), Err((2, ErrorKind::TruncatedEscape)));
+}
+
+#[test]
+fn round_trip() {
+    // xorshift, good enough to throw random bytes at the codec
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..10_000 {
+        let len = next() % 32;
+        let bytes = (0..len).map(|_| next() as u8).collect::<Vec<_>>();
+        for escapes in [Escapes::All, Escapes::Quotes] {
+            let literal = encode(&bytes, escapes);
+            assert_eq!(decode(&literal), Ok(bytes.clone()));
+        }
+    }
+
+    for line in INPUT.lines().filter(|l| !l.is_empty()) {
+        let decoded = decode(line.as_bytes()).unwrap();
+        assert_eq!(decode(&encode(&decoded, Escapes::All)), Ok(decoded));
+        for escapes in [Escapes::All, Escapes::Quotes] {
+            let literal = encode(line.as_bytes(), escapes);
+            assert_eq!(decode(&literal), Ok(line.as_bytes().to_vec()));
+        }
+    }
 }
 
 const INPUT: &str = r#"
@@ -37,87 +37,292 @@ impl<'a> Line<'a> {
     }
 }
 
-fn recurse_min(
-    distances: &HashMap<(usize, usize), Distance>,
-    visited: &mut [bool],
-    current: usize,
-) -> Distance {
-    if visited.iter().all(bool::clone) {
-        return 0;
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum RouteError {
+    /// The Held-Karp table for this many cities does not fit in memory.
+    TooManyCities(usize),
+    /// A route is longer than `Distance` can hold.
+    Overflow,
+}
 
-    let n_cities = visited.len();
-    let mut ret = Distance::MAX;
-    for city in 0..n_cities {
-        if visited[city] {
-            continue;
-        }
-        visited[city] = true;
-        let distance = distances[&(current, city)];
-        ret = ret.min(recurse_min(distances, visited, city) + distance);
-        visited[city] = false;
-    }
+fn try_vec<T: Clone>(len: usize, value: T) -> Option<Vec<T>> {
+    let mut ret = Vec::new();
+    ret.try_reserve_exact(len).ok()?;
+    ret.resize(len, value);
+    Some(ret)
+}
 
-    ret
+/// `get(visited, last)` is the best distance of a route which covers the
+/// `visited` set of cities and stops at `last`, `None` if there is none,
+/// e.g. because of missing roads. Reachability is kept in its own bits so
+/// that every distance stays usable.
+struct Table {
+    n: usize,
+    distances: Vec<Distance>,
+    reached: Vec<u64>,
 }
 
-fn recurse_max(
-    distances: &HashMap<(usize, usize), Distance>,
-    visited: &mut [bool],
-    current: usize,
-) -> Distance {
-    if visited.iter().all(bool::clone) {
-        return 0;
+impl Table {
+    /// `None` if the `2^n·n` entries do not fit in memory.
+    fn new(n: usize) -> Option<Self> {
+        let subsets = 1usize.checked_shl(u32::try_from(n).ok()?)?;
+        let len = subsets.checked_mul(n)?;
+        Some(Self {
+            n,
+            distances: try_vec(len, 0)?,
+            reached: try_vec(len.div_ceil(64), 0)?,
+        })
     }
 
-    let n_cities = visited.len();
-    let mut ret = 0;
-    for city in 0..n_cities {
-        if visited[city] {
-            continue;
-        }
-        visited[city] = true;
-        let distance = distances[&(current, city)];
-        ret = ret.max(recurse_max(distances, visited, city) + distance);
-        visited[city] = false;
+    fn get(&self, visited: usize, last: usize) -> Option<Distance> {
+        let i = visited * self.n + last;
+        (self.reached[i / 64] >> (i % 64) & 1 != 0).then(|| self.distances[i])
     }
 
-    ret
+    fn set(&mut self, visited: usize, last: usize, distance: Distance) {
+        let i = visited * self.n + last;
+        self.reached[i / 64] |= 1 << (i % 64);
+        self.distances[i] = distance;
+    }
 }
 
-fn solve_both(input: &str) -> (Distance, Distance) {
-    let mut distances = HashMap::new();
-    let mut int = Interner::default();
-    for Line { from, to, distance } in input.lines().filter(|l| !l.is_empty()).map(Line::parse) {
-        let from = int.insert(from);
-        let to = int.insert(to);
-        distances.insert((from, to), distance);
-        distances.insert((to, from), distance);
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    Shortest,
+    Longest,
+}
+
+impl Goal {
+    fn is_better(self, new: Distance, old: Option<Distance>) -> bool {
+        old.is_none_or(|old| match self {
+            Self::Shortest => new < old,
+            Self::Longest => new > old,
+        })
     }
+}
 
-    let n_cities = int.strs.len();
-    let mut visited = vec![false; n_cities];
-    let mut best = Distance::MAX;
-    let mut worst = 0;
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    /// Visit every city once, ending anywhere.
+    Path,
+    /// Visit every city once and return to the first one.
+    Tour,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Route<'a> {
+    distance: Distance,
+    /// Cities in visiting order, for tours the first one is not repeated at
+    /// the end.
+    cities: Vec<&'a str>,
+}
+
+struct Map<'a> {
+    int: Interner<'a>,
+    /// `None` if there is no road between the cities.
+    distances: Vec<Vec<Option<Distance>>>,
+}
 
-    for city in 0..n_cities {
-        visited[city] = true;
-        best = recurse_min(&distances, &mut visited, city).min(best);
-        visited[city] = false;
+impl<'a> Map<'a> {
+    fn parse(input: &'a str) -> Self {
+        let mut int = Interner::default();
+        let mut roads = Vec::new();
+        for Line { from, to, distance } in input.lines().filter(|l| !l.is_empty()).map(Line::parse)
+        {
+            roads.push((int.insert(from), int.insert(to), distance));
+        }
+
+        let n_cities = int.strs.len();
+        let mut distances = vec![vec![None; n_cities]; n_cities];
+        for (from, to, distance) in roads {
+            distances[from][to] = Some(distance);
+            distances[to][from] = Some(distance);
+        }
+        Self { int, distances }
     }
 
-    for city in 0..n_cities {
-        visited[city] = true;
-        worst = recurse_max(&distances, &mut visited, city).max(worst);
-        visited[city] = false;
+    /// Held-Karp over every subset of cities, O(2^n·n²) time and 2^n·n
+    /// memory. `Ok(None)` if missing roads or an unknown `start` leave no
+    /// route at all.
+    fn best_route(
+        &self,
+        goal: Goal,
+        shape: Shape,
+        start: Option<&str>,
+    ) -> Result<Option<Route<'a>>, RouteError> {
+        let n = self.int.strs.len();
+        if n == 0 {
+            return Ok(None);
+        }
+        let add = |a: Distance, b: Distance| a.checked_add(b).ok_or(RouteError::Overflow);
+        let start = match start {
+            Some(name) => match self.int.bag.get(name) {
+                Some(&city) => Some(city),
+                None => return Ok(None),
+            },
+            // rotating a tour does not change it, so any city may go first
+            None if shape == Shape::Tour => Some(0),
+            None => None,
+        };
+
+        let mut table = Table::new(n).ok_or(RouteError::TooManyCities(n))?;
+        let full = (1usize << n) - 1;
+        for city in 0..n {
+            if start.is_none_or(|s| s == city) {
+                table.set(1 << city, city, 0);
+            }
+        }
+
+        for visited in 1..=full {
+            for last in 0..n {
+                let Some(here) = table.get(visited, last) else {
+                    continue;
+                };
+                for next in (0..n).filter(|&c| visited & (1 << c) == 0) {
+                    let Some(d) = self.distances[last][next] else {
+                        continue;
+                    };
+                    let there = add(here, d)?;
+                    if goal.is_better(there, table.get(visited | 1 << next, next)) {
+                        table.set(visited | 1 << next, next, there);
+                    }
+                }
+            }
+        }
+
+        // total distance including the closing road for tours
+        let mut best: Option<(usize, Distance)> = None;
+        for last in 0..n {
+            let Some(here) = table.get(full, last) else {
+                continue;
+            };
+            let total = match shape {
+                Shape::Path => here,
+                Shape::Tour if n == 1 => here,
+                Shape::Tour => match self.distances[last][start.unwrap()] {
+                    Some(d) => add(here, d)?,
+                    None => continue,
+                },
+            };
+            if goal.is_better(total, best.map(|(_, d)| d)) {
+                best = Some((last, total));
+            }
+        }
+        let Some((mut last, distance)) = best else {
+            return Ok(None);
+        };
+
+        // walk back through the table looking for the predecessor which
+        // produced each entry, the sums were all checked on the way in
+        let mut order = vec![last];
+        let mut visited = full;
+        while visited.count_ones() > 1 {
+            let here = table.get(visited, last);
+            let prev_visited = visited & !(1 << last);
+            let prev = (0..n)
+                .find(|&prev| {
+                    let before = table.get(prev_visited, prev);
+                    let road = self.distances[prev][last];
+                    before.zip(road).map(|(b, d)| b + d) == here
+                })
+                .unwrap();
+            order.push(prev);
+            visited = prev_visited;
+            last = prev;
+        }
+        order.reverse();
+
+        Ok(Some(Route {
+            distance,
+            cities: order.into_iter().map(|c| self.int.strs[c]).collect(),
+        }))
     }
+}
 
-    (best, worst)
+fn solve_both(input: &str) -> (Distance, Distance) {
+    let map = Map::parse(input);
+    let best = map.best_route(Goal::Shortest, Shape::Path, None);
+    let worst = map.best_route(Goal::Longest, Shape::Path, None);
+    let (best, worst) = (best.unwrap().unwrap(), worst.unwrap().unwrap());
+    (best.distance, worst.distance)
 }
 
 #[test]
 fn example() {
     assert_eq!(solve_both(INPUT), (605, 982));
+
+    let map = Map::parse(INPUT);
+    let route = |goal, shape, start| {
+        let route = map.best_route(goal, shape, start).unwrap().unwrap();
+        (route.distance, route.cities)
+    };
+    assert_eq!(
+        route(Goal::Shortest, Shape::Path, Some("London")),
+        (605, vec!["London", "Dublin", "Belfast"])
+    );
+    assert_eq!(
+        route(Goal::Longest, Shape::Path, Some("Belfast")),
+        (982, vec!["Belfast", "London", "Dublin"])
+    );
+    assert_eq!(route(Goal::Shortest, Shape::Tour, None).0, 1123);
+    assert_eq!(
+        map.best_route(Goal::Shortest, Shape::Path, Some("Paris")),
+        Ok(None)
+    );
+}
+
+#[test]
+fn missing_roads() {
+    let map = Map::parse("London to Dublin = 464\nLondon to Belfast = 518\n");
+    let route = map.best_route(Goal::Shortest, Shape::Path, None);
+    let route = route.unwrap().unwrap();
+    assert_eq!(route.distance, 982);
+    assert_eq!(route.cities[1], "London");
+    assert_eq!(
+        map.best_route(Goal::Shortest, Shape::Path, Some("London")),
+        Ok(None)
+    );
+    assert_eq!(map.best_route(Goal::Shortest, Shape::Tour, None), Ok(None));
+
+    // cities on a line with roads only between neighbours
+    let input = (1..16)
+        .map(|i| format!("c{} to c{i} = {i}\n", i - 1))
+        .collect::<String>();
+    let map = Map::parse(&input);
+    let route = map
+        .best_route(Goal::Longest, Shape::Path, Some("c0"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(route.distance, (1..16).sum());
+    assert_eq!(
+        route.cities,
+        (0..16).map(|i| format!("c{i}")).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn limits() {
+    // a road as long as `Distance` allows is still a road
+    let map = Map::parse("a to b = 4294967295\n");
+    let route = map.best_route(Goal::Longest, Shape::Path, None);
+    assert_eq!(route.unwrap().unwrap().distance, Distance::MAX);
+
+    let map = Map::parse("a to b = 4294967295\nb to c = 1\n");
+    assert_eq!(
+        map.best_route(Goal::Longest, Shape::Path, None),
+        Err(RouteError::Overflow)
+    );
+
+    // far beyond any memory, the table is never allocated
+    for n in [48, 64, 100] {
+        let input = (1..n)
+            .map(|i| format!("c0 to c{i} = 1\n"))
+            .collect::<String>();
+        assert_eq!(
+            Map::parse(&input).best_route(Goal::Shortest, Shape::Path, None),
+            Err(RouteError::TooManyCities(n))
+        );
+    }
 }
 
 const INPUT: &str = "London to Dublin = 464
@@ -1,5 +1,19 @@
 #![cfg(test)]
 
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
+
+use std::collections::{HashMap, HashSet};
+
+// lengths grow by ~1.3 a step, passing 64 bits after about 170 of them
+#[cfg(not(any(aoc_checked, aoc_wide)))]
+type Len = u64;
+#[cfg(aoc_checked)]
+type Len = numeric::Checked<u64>;
+#[cfg(aoc_wide)]
+type Len = numeric::Checked<numeric::U256>;
+
 struct Prefix {
     elem: u8,
     times: usize,
@@ -27,28 +41,224 @@ fn step(mut from: &str, to: &mut String) {
     }
 }
 
-fn solve_both(input: &str, n_steps_first: usize, n_steps_second: usize) -> (usize, usize) {
-    let mut from = String::from(input);
-    let mut to = String::new();
+fn stepped(s: &str) -> String {
+    let mut ret = String::new();
+    step(s, &mut ret);
+    ret
+}
+
+/// Like `step`, but drops the last run, whose length may be cut short when
+/// `s` is only a prefix.
+fn step_prefix(mut from: &str) -> String {
+    use std::fmt::Write;
+
+    let mut ret = String::new();
+    while let Some(Prefix { elem, times }) = Prefix::of(&mut from) {
+        if from.is_empty() {
+            break;
+        }
+        let elem = elem - b'0';
+        _ = write!(ret, "{times}{elem}");
+    }
+    ret
+}
+
+const SPLIT_PREFIX: usize = 64;
+const SPLIT_STEPS: usize = 100;
+
+/// Whether a string ending with `last` and `right` evolve independently.
+/// Look-and-say keeps the last digit of a string, so they do as long as no
+/// descendant of `right` starts with `last`. Only a prefix of `right` is
+/// followed, and the answer is "yes" only once that prefix comes back to a
+/// state seen before, after which its first digits repeat forever. When it
+/// does not within `SPLIT_STEPS`, the answer is "no", which keeps a larger
+/// atom that is expanded as a whole.
+fn splits(last: u8, right: &str) -> bool {
+    let mut exact = right.len() <= SPLIT_PREFIX;
+    let mut cur = right[..right.len().min(SPLIT_PREFIX)].to_owned();
+    let mut seen = HashSet::new();
+    for _ in 0..SPLIT_STEPS {
+        if cur.is_empty() || cur.as_bytes()[0] == last {
+            return false;
+        }
+        if !seen.insert((cur.clone(), exact)) {
+            return true;
+        }
+        cur = if exact {
+            stepped(&cur)
+        } else {
+            step_prefix(&cur)
+        };
+        if cur.len() > SPLIT_PREFIX {
+            cur.truncate(SPLIT_PREFIX);
+            exact = false;
+        }
+    }
+    false
+}
+
+/// Splits `s` into atoms, the smallest parts which evolve independently.
+fn split(s: &str) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let mut start = 0;
+    for i in 1..s.len() {
+        if splits(s.as_bytes()[i - 1], &s[i..]) {
+            ret.push(&s[start..i]);
+            start = i;
+        }
+    }
+    ret.push(&s[start..]);
+    ret
+}
+
+/// Atoms and what each of them turns into after one step.
+struct Table {
+    atoms: Vec<String>,
+    index: HashMap<String, usize>,
+    /// Filled for a prefix of `atoms`, the rest are waiting in `insert`.
+    decay: Vec<Vec<usize>>,
+}
+
+impl Table {
+    /// Conway's 92 common elements, all of which descend from uranium, `3`.
+    fn elements() -> Self {
+        let mut ret = Self {
+            atoms: Vec::new(),
+            index: HashMap::new(),
+            decay: Vec::new(),
+        };
+        ret.insert("3");
+        ret
+    }
+
+    fn intern(&mut self, atom: &str) -> usize {
+        if let Some(&id) = self.index.get(atom) {
+            return id;
+        }
+        let id = self.atoms.len();
+        self.atoms.push(atom.to_owned());
+        self.index.insert(atom.to_owned(), id);
+        id
+    }
+
+    /// Adds `atom` along with everything it decays into.
+    fn insert(&mut self, atom: &str) -> usize {
+        let ret = self.intern(atom);
+        while self.decay.len() < self.atoms.len() {
+            let next = stepped(&self.atoms[self.decay.len()]);
+            let products = split(&next)
+                .into_iter()
+                .map(|product| self.intern(product))
+                .collect();
+            self.decay.push(products);
+        }
+        ret
+    }
 
-    for _ in 0..n_steps_first {
-        step(&from, &mut to);
-        std::mem::swap(&mut from, &mut to);
-        to.clear();
+    /// Atom ids of `s`, `None` if it has parts missing from the table.
+    fn decompose(&self, s: &str) -> Option<Vec<usize>> {
+        split(s)
+            .into_iter()
+            .map(|atom| self.index.get(atom).copied())
+            .collect()
     }
-    let first = from.len();
 
-    for _ in n_steps_first..n_steps_second {
-        step(&from, &mut to);
-        std::mem::swap(&mut from, &mut to);
-        to.clear();
+    /// Length of `seed` after `n_steps`, computed from atom counts without
+    /// building the string. Seeds which do not decompose into known atoms
+    /// (e.g. with digits above 3) get their own atoms added to the table.
+    fn length_after(&mut self, seed: &str, n_steps: usize) -> Len {
+        let atoms = self.decompose(seed).unwrap_or_else(|| {
+            split(seed)
+                .into_iter()
+                .map(|atom| self.insert(atom))
+                .collect()
+        });
+
+        let mut counts = vec![Len::from(0u8); self.atoms.len()];
+        for atom in atoms {
+            counts[atom] += Len::from(1u8);
+        }
+        for _ in 0..n_steps {
+            let mut next = vec![Len::from(0u8); self.atoms.len()];
+            for (atom, &count) in counts.iter().enumerate() {
+                for &product in &self.decay[atom] {
+                    next[product] += count;
+                }
+            }
+            counts = next;
+        }
+
+        counts
+            .into_iter()
+            .zip(&self.atoms)
+            .map(|(count, atom)| count * Len::from(atom.len() as u64))
+            .sum()
     }
-    let second = from.len();
+}
 
-    (first, second)
+fn solve_both(input: &str, n_steps_first: usize, n_steps_second: usize) -> (Len, Len) {
+    let mut table = Table::elements();
+    (
+        table.length_after(input, n_steps_first),
+        table.length_after(input, n_steps_second),
+    )
 }
 
 #[test]
 fn user_input() {
-    assert_eq!(solve_both("1113122113", 40, 50), (360154, 5103798));
+    assert_eq!(
+        solve_both("1113122113", 40, 50),
+        (Len::from(360154u64), Len::from(5103798u64))
+    );
+    let mut table = Table::elements();
+    assert_eq!(
+        table.length_after("1113122113", 100),
+        Len::from(2915092038886u64)
+    );
+    assert_eq!(
+        table.length_after("1", 150),
+        Len::from(380720811120876076u64)
+    );
+}
+
+#[cfg(aoc_checked)]
+#[test]
+#[should_panic(expected = "arithmetic overflow")]
+fn overflows_64_bits() {
+    Table::elements().length_after("1", 200);
+}
+
+#[cfg(aoc_wide)]
+#[test]
+fn past_64_bits() {
+    // 6026901954569697226 when wrapped to 64 bits
+    let mut table = Table::elements();
+    assert_eq!(
+        table.length_after("1", 200),
+        "217493139530990183249866".parse().unwrap()
+    );
+    assert_eq!(
+        table.length_after("1113122113", 200),
+        "951327517212589375047678".parse().unwrap()
+    );
+}
+
+#[test]
+fn matches_strings() {
+    let mut table = Table::elements();
+    assert_eq!(table.atoms.len(), 92);
+    assert_eq!(table.decompose("22"), Some(vec![table.index["22"]]));
+    assert_eq!(table.decompose("4"), None);
+
+    for seed in ["1", "3", "22", "1111111111", "4", "12345", "3113322113"] {
+        let mut s = seed.to_owned();
+        for n_steps in 0..25 {
+            assert_eq!(
+                table.length_after(seed, n_steps),
+                Len::from(s.len() as u64),
+                "{seed} after {n_steps} steps"
+            );
+            s = stepped(&s);
+        }
+    }
 }
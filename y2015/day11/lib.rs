@@ -1,83 +1,212 @@
 #![cfg(test)]
 
-const LEN: usize = 8;
-type Bytes = [u8; LEN];
+const ALPHABET: u8 = b'z' - b'a' + 1;
 
-struct Password(Bytes);
+#[derive(Clone)]
+struct Password(Vec<u8>);
 
 impl Password {
     fn from_str(s: &str) -> Self {
-        let mut bytes = <&Bytes>::try_from(s.as_bytes()).unwrap().clone();
-        bytes.iter_mut().for_each(|b| *b -= b'a');
-        Self(bytes)
+        assert!(
+            s.bytes().all(|b| b.is_ascii_lowercase()),
+            "bad password {s:?}"
+        );
+        Self(s.bytes().map(|b| b - b'a').collect())
     }
 
-    fn increment(&mut self) {
-        const MAX: u8 = b'z' - b'a';
-
-        for digit in self.0.iter_mut().rev() {
+    /// Moves to the first password not starting with the `len` first
+    /// letters of this one. Returns `false` on running out of passwords.
+    fn skip_prefix(&mut self, len: usize) -> bool {
+        self.0[len..].iter_mut().for_each(|digit| *digit = 0);
+        for digit in self.0[..len].iter_mut().rev() {
             *digit += 1;
-            if *digit > MAX {
+            if *digit == ALPHABET {
                 *digit = 0;
                 continue;
             }
-            break;
+            return true;
         }
+        false
+    }
+
+    fn increment(&mut self) -> bool {
+        self.skip_prefix(self.0.len())
     }
 }
 
-fn has_no_confusables(bytes: &[u8]) -> bool {
-    bytes
-        .iter()
-        .all(|&b| !matches!(b + b'a', b'i' | b'o' | b'l'))
+impl std::fmt::Display for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0
+            .iter()
+            .try_for_each(|&b| write!(f, "{}", (b + b'a') as char))
+    }
 }
 
-fn has_ascend(bytes: &[u8]) -> bool {
-    bytes
-        .windows(3)
-        .map(|w| <&[u8; 3]>::try_from(w).unwrap())
-        .any(|&[a, b, c]| b.wrapping_sub(a) == 1 && c.wrapping_sub(a) == 2)
+trait Rule {
+    fn check(&self, bytes: &[u8]) -> bool;
+
+    /// Length of a prefix of `bytes` which no valid password starts with,
+    /// lets the search skip all of them at once.
+    fn bad_prefix(&self, _bytes: &[u8]) -> Option<usize> {
+        None
+    }
 }
 
-fn has_different_pairs(bytes: &[u8]) -> bool {
-    let mut seen = [false; (b'z' - b'a' + 1) as usize];
-    let mut n_seen = 0;
-
-    for b in bytes
-        .iter()
-        .zip(&bytes[1..])
-        .filter_map(|(&a, &b)| (a == b).then_some(a))
-    {
-        if !std::mem::replace(&mut seen[b as usize], true) {
-            n_seen += 1;
+/// Letters which must not appear anywhere.
+struct Forbid(Vec<u8>);
+
+impl Forbid {
+    fn confusables() -> Self {
+        Self(b"iol".iter().map(|b| b - b'a').collect())
+    }
+}
+
+impl Rule for Forbid {
+    fn check(&self, bytes: &[u8]) -> bool {
+        self.bad_prefix(bytes).is_none()
+    }
+
+    fn bad_prefix(&self, bytes: &[u8]) -> Option<usize> {
+        bytes
+            .iter()
+            .position(|b| self.0.contains(b))
+            .map(|pos| pos + 1)
+    }
+}
+
+/// A run of this many consecutive increasing letters, like `abc`.
+struct Straight(usize);
+
+impl Straight {
+    fn new(len: usize) -> Self {
+        assert_ne!(len, 0, "a straight needs at least one letter");
+        Self(len)
+    }
+}
+
+impl Rule for Straight {
+    fn check(&self, bytes: &[u8]) -> bool {
+        bytes
+            .windows(self.0)
+            .any(|w| w.windows(2).all(|pair| pair[1] == pair[0] + 1))
+    }
+}
+
+/// At least this many pairs of repeated letters, all with different letters.
+struct Pairs(usize);
+
+impl Rule for Pairs {
+    fn check(&self, bytes: &[u8]) -> bool {
+        let mut seen = [false; ALPHABET as usize];
+        let mut n_seen = 0;
+
+        for b in bytes
+            .windows(2)
+            .filter_map(|pair| (pair[0] == pair[1]).then_some(pair[0]))
+        {
+            if !std::mem::replace(&mut seen[b as usize], true) {
+                n_seen += 1;
+            }
         }
-        if n_seen >= 2 {
-            return true;
+
+        n_seen >= self.0
+    }
+}
+
+struct Policy(Vec<Box<dyn Rule>>);
+
+impl Policy {
+    /// The rules from the puzzle.
+    fn santa() -> Self {
+        Self(vec![
+            Box::new(Forbid::confusables()),
+            Box::new(Straight::new(3)),
+            Box::new(Pairs(2)),
+        ])
+    }
+
+    fn is_valid(&self, bytes: &[u8]) -> bool {
+        self.0.iter().all(|rule| rule.check(bytes))
+    }
+
+    /// Valid passwords following `password` in order, ending at the last
+    /// password of the same length.
+    fn successors(&self, password: &str) -> Successors<'_> {
+        Successors {
+            policy: self,
+            password: Some(Password::from_str(password)),
         }
     }
+}
 
-    false
+struct Successors<'p> {
+    policy: &'p Policy,
+    password: Option<Password>,
 }
 
-fn is_valid(bytes: &Bytes) -> bool {
-    has_no_confusables(bytes) && has_ascend(bytes) && has_different_pairs(bytes)
+impl Iterator for Successors<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let password = self.password.as_mut()?;
+        let mut more = password.increment();
+        while more {
+            let bad_prefix = self
+                .policy
+                .0
+                .iter()
+                .filter_map(|rule| rule.bad_prefix(&password.0))
+                .min();
+            if let Some(len) = bad_prefix {
+                more = password.skip_prefix(len);
+            } else if self.policy.is_valid(&password.0) {
+                return Some(password.to_string());
+            } else {
+                more = password.increment();
+            }
+        }
+        self.password = None;
+        None
+    }
 }
 
 fn solve(input: &str) -> String {
-    let mut password = Password::from_str(input);
-    let mut answer = std::iter::from_fn(|| {
-        password.increment();
-        Some(password.0)
-    })
-    .filter(is_valid)
-    .next()
-    .unwrap();
-    answer.iter_mut().for_each(|b| *b += b'a');
-    std::str::from_utf8(&answer).unwrap().to_owned()
+    Policy::santa().successors(input).next().unwrap()
 }
 
 #[test]
 fn example() {
     assert_eq!(solve("abcdefgh"), "abcdffaa");
     assert_eq!(solve("ghijklmn"), "ghjaabcc");
+
+    let policy = Policy::santa();
+    let next = policy.successors("abcdffaa").take(2).collect::<Vec<_>>();
+    assert_eq!(next, ["abcdffbb", "abcdffcc"]);
+}
+
+#[test]
+fn custom_policies() {
+    let policy = Policy(vec![Box::new(Forbid::confusables()), Box::new(Pairs(1))]);
+    let next = policy.successors("hz").collect::<Vec<_>>();
+    assert_eq!(
+        next,
+        [
+            "jj", "kk", "mm", "nn", "pp", "qq", "rr", "ss", "tt", "uu", "vv", "ww", "xx", "yy",
+            "zz"
+        ]
+    );
+
+    let policy = Policy(vec![Box::new(Straight::new(4))]);
+    assert_eq!(
+        policy.successors("abcdefghiz").next().unwrap(),
+        "abcdefghja"
+    );
+    assert_eq!(policy.successors("zzzz").next(), None);
+    assert_eq!(policy.successors("wxyx").collect::<Vec<_>>(), ["wxyz"]);
+}
+
+#[test]
+#[should_panic(expected = "a straight needs at least one letter")]
+fn empty_straight() {
+    Straight::new(0);
 }
@@ -1,41 +1,714 @@
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde_json::Value as Json;
+use std::fmt;
+use std::io::{BufReader, Read};
 
-#[derive(Default)]
-struct Adder {
-    total: i64,
+// Query language, e.g. `sum numbers where not object.any(value == "red")`:
+//
+//     query := ("sum" | "count") "numbers" ["where" pred]
+//     pred  := and ("or" and)*
+//     and   := unary ("and" unary)*
+//     unary := "not" unary | "(" pred ")" | atom
+//     atom  := ("object" | "array") "." ("any" | "all") "(" cond ")"
+//            | "object" | "array" | "number" | "string"
+//            | "key" ("==" | "!=") STRING
+//            | "depth" ("==" | "!=" | "<" | "<=" | ">" | ">=") NUMBER
+//     cond  := ("value" | "key") ("==" | "!=") literal
+//
+// A node failing the predicate is skipped along with everything inside it.
+// `depth` is 0 for the whole document, `key` is the property name of the
+// node in its parent object, and `any`/`all` look at direct children only.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Object,
+    Array,
+    Number,
+    String,
+    Bool,
+    Null,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Num(i64),
+    Str(String),
+}
+
+/// Direct child of a container as seen by `any`/`all`.
+#[derive(Clone, Copy)]
+enum Child<'a> {
+    Scalar(&'a Literal),
+    Container,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmp {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Cmp {
+    fn holds(self, a: usize, b: usize) -> bool {
+        match self {
+            Self::Eq => a == b,
+            Self::Lt => a < b,
+            Self::Le => a <= b,
+            Self::Gt => a > b,
+            Self::Ge => a >= b,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Cond {
+    Value(Literal),
+    Key(String),
+    Not(Box<Cond>),
+}
+
+impl Cond {
+    fn matches(&self, key: Option<&str>, child: Child<'_>) -> bool {
+        match self {
+            Self::Value(lit) => matches!(child, Child::Scalar(v) if v == lit),
+            Self::Key(k) => key == Some(k.as_str()),
+            Self::Not(cond) => !cond.matches(key, child),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Pred {
+    Not(Box<Pred>),
+    And(Box<Pred>, Box<Pred>),
+    Or(Box<Pred>, Box<Pred>),
+    Is(Kind),
+    Key(String),
+    Depth(Cmp, usize),
+    /// Index into `Query::conds`.
+    Any(Kind, usize),
+    All(Kind, usize),
+}
+
+/// What predicates know about a node.
+struct Node<'a> {
+    kind: Kind,
+    key: Option<&'a str>,
+    depth: usize,
+    /// Per `Query::conds`, whether any and whether all children match.
+    children: &'a [(bool, bool)],
+}
+
+impl Pred {
+    fn eval(&self, node: &Node<'_>) -> bool {
+        match self {
+            Self::Not(p) => !p.eval(node),
+            Self::And(a, b) => a.eval(node) && b.eval(node),
+            Self::Or(a, b) => a.eval(node) || b.eval(node),
+            Self::Is(kind) => node.kind == *kind,
+            Self::Key(k) => node.key == Some(k.as_str()),
+            Self::Depth(cmp, n) => cmp.holds(node.depth, *n),
+            Self::Any(kind, cond) => node.kind == *kind && node.children[*cond].0,
+            Self::All(kind, cond) => node.kind == *kind && node.children[*cond].1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Aggregate {
+    Sum,
+    Count,
+}
+
+#[derive(Debug, PartialEq)]
+struct Query {
+    aggregate: Aggregate,
+    pred: Option<Pred>,
+    conds: Vec<Cond>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Num(i64),
+    Punct(&'static str),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    const PUNCTS: [&str; 9] = ["==", "!=", "<=", ">=", "<", ">", "(", ")", "."];
+
+    let mut ret = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let ch = rest.chars().next().unwrap();
+        if let Some(&p) = PUNCTS.iter().find(|p| rest.starts_with(*p)) {
+            ret.push(Token::Punct(p));
+            rest = &rest[p.len()..];
+        } else if ch == '"' {
+            let end = rest[1..]
+                .find('"')
+                .ok_or_else(|| format!("unterminated string in {s:?}"))?;
+            ret.push(Token::Str(rest[1..end + 1].to_owned()));
+            rest = &rest[end + 2..];
+        } else if ch == '-' || ch.is_ascii_digit() {
+            let end = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |e| e + 1);
+            let n = rest[..end]
+                .parse()
+                .map_err(|_| format!("bad number {:?}", &rest[..end]))?;
+            ret.push(Token::Num(n));
+            rest = &rest[end..];
+        } else if ch.is_ascii_alphabetic() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            ret.push(Token::Word(rest[..end].to_owned()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected {ch:?} in {s:?}"));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(ret)
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+    conds: Vec<Cond>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        self.peek();
+        self.peeked
+            .take()
+            .ok_or_else(|| "unexpected end of query".to_owned())
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w == word);
+        if found {
+            self.peeked = None;
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected {expected:?}, found {token:?}")),
+        }
+    }
+
+    fn pred(&mut self) -> Result<Pred, String> {
+        let mut ret = self.and()?;
+        while self.eat_word("or") {
+            ret = Pred::Or(Box::new(ret), Box::new(self.and()?));
+        }
+        Ok(ret)
+    }
+
+    fn and(&mut self) -> Result<Pred, String> {
+        let mut ret = self.unary()?;
+        while self.eat_word("and") {
+            ret = Pred::And(Box::new(ret), Box::new(self.unary()?));
+        }
+        Ok(ret)
+    }
+
+    fn unary(&mut self) -> Result<Pred, String> {
+        if self.eat_word("not") {
+            return Ok(Pred::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Punct("(")) {
+            self.next()?;
+            let ret = self.pred()?;
+            self.expect(Token::Punct(")"))?;
+            return Ok(ret);
+        }
+        self.atom()
+    }
+
+    /// `==` or `!=`, the latter reported as `true`.
+    fn equality(&mut self) -> Result<bool, String> {
+        match self.next()? {
+            Token::Punct("==") => Ok(false),
+            Token::Punct("!=") => Ok(true),
+            token => Err(format!("expected == or !=, found {token:?}")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Str(s) => Ok(s),
+            token => Err(format!("expected a string, found {token:?}")),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Pred, String> {
+        let word = match self.next()? {
+            Token::Word(w) => w,
+            token => return Err(format!("expected a predicate, found {token:?}")),
+        };
+        let kind = match word.as_str() {
+            "object" => Kind::Object,
+            "array" => Kind::Array,
+            "number" => Kind::Number,
+            "string" => Kind::String,
+            "key" => {
+                let negate = self.equality()?;
+                let ret = Pred::Key(self.string()?);
+                return Ok(if negate {
+                    Pred::Not(Box::new(ret))
+                } else {
+                    ret
+                });
+            }
+            "depth" => {
+                let (cmp, negate) = match self.next()? {
+                    Token::Punct("==") => (Cmp::Eq, false),
+                    Token::Punct("!=") => (Cmp::Eq, true),
+                    Token::Punct("<") => (Cmp::Lt, false),
+                    Token::Punct("<=") => (Cmp::Le, false),
+                    Token::Punct(">") => (Cmp::Gt, false),
+                    Token::Punct(">=") => (Cmp::Ge, false),
+                    token => return Err(format!("expected a comparison, found {token:?}")),
+                };
+                let depth = match self.next()? {
+                    Token::Num(n) if n >= 0 => n as usize,
+                    token => return Err(format!("expected a depth, found {token:?}")),
+                };
+                let ret = Pred::Depth(cmp, depth);
+                return Ok(if negate {
+                    Pred::Not(Box::new(ret))
+                } else {
+                    ret
+                });
+            }
+            _ => return Err(format!("unknown predicate {word:?}")),
+        };
+
+        if self.peek() != Some(&Token::Punct(".")) {
+            return Ok(Pred::Is(kind));
+        }
+        self.next()?;
+        if !matches!(kind, Kind::Object | Kind::Array) {
+            return Err(format!("{word} has no children"));
+        }
+        let quantifier = match self.next()? {
+            Token::Word(w) if w == "any" || w == "all" => w,
+            token => return Err(format!("expected any or all, found {token:?}")),
+        };
+        self.expect(Token::Punct("("))?;
+        let cond = self.cond()?;
+        self.expect(Token::Punct(")"))?;
+
+        self.conds.push(cond);
+        let idx = self.conds.len() - 1;
+        Ok(match quantifier.as_str() {
+            "any" => Pred::Any(kind, idx),
+            _ => Pred::All(kind, idx),
+        })
+    }
+
+    fn cond(&mut self) -> Result<Cond, String> {
+        let ret = if self.eat_word("value") {
+            let negate = self.equality()?;
+            let lit = match self.next()? {
+                Token::Str(s) => Literal::Str(s),
+                Token::Num(n) => Literal::Num(n),
+                Token::Word(w) if w == "true" => Literal::Bool(true),
+                Token::Word(w) if w == "false" => Literal::Bool(false),
+                Token::Word(w) if w == "null" => Literal::Null,
+                token => return Err(format!("expected a literal, found {token:?}")),
+            };
+            (negate, Cond::Value(lit))
+        } else if self.eat_word("key") {
+            let negate = self.equality()?;
+            (negate, Cond::Key(self.string()?))
+        } else {
+            return Err(format!("expected value or key, found {:?}", self.peek()));
+        };
+        Ok(match ret {
+            (true, cond) => Cond::Not(Box::new(cond)),
+            (false, cond) => cond,
+        })
+    }
+}
+
+impl Query {
+    fn parse(s: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter(),
+            peeked: None,
+            conds: Vec::new(),
+        };
+
+        let aggregate = if parser.eat_word("sum") {
+            Aggregate::Sum
+        } else if parser.eat_word("count") {
+            Aggregate::Count
+        } else {
+            return Err(format!("expected sum or count, found {:?}", parser.peek()));
+        };
+        if !parser.eat_word("numbers") {
+            return Err(format!("expected numbers, found {:?}", parser.peek()));
+        }
+        let pred = if parser.eat_word("where") {
+            Some(parser.pred()?)
+        } else {
+            None
+        };
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {token:?} after the query"));
+        }
+
+        Ok(Self {
+            aggregate,
+            pred,
+            conds: parser.conds,
+        })
+    }
+
+    fn accepts(&self, node: &Node<'_>) -> bool {
+        self.pred.as_ref().is_none_or(|p| p.eval(node))
+    }
+}
+
+/// Wide enough that no document of `i64` numbers can overflow it.
+type Total = i128;
+
+enum Event {
+    Start(Kind),
+    End,
+    Key(String),
+    Scalar(Literal),
+}
+
+struct Frame {
+    kind: Kind,
+    key: Option<String>,
+    /// Key of the property being read, for objects.
+    pending_key: Option<String>,
+    total: Total,
+    children: Vec<(bool, bool)>,
+}
+
+/// Runs a query over a stream of events, keeping state only for the
+/// containers currently open.
+struct Evaluator<'q> {
+    query: &'q Query,
+    stack: Vec<Frame>,
+    result: Total,
 }
 
-impl Adder {
-    fn visit(&mut self, value: &Json, reject: &dyn Fn(&Json) -> bool) {
-        use Json::*;
+impl<'q> Evaluator<'q> {
+    fn new(query: &'q Query) -> Self {
+        Self {
+            query,
+            stack: Vec::new(),
+            result: 0,
+        }
+    }
 
-        if reject(value) {
+    /// Adds an accepted `value` to the enclosing container and lets it know
+    /// about its new child.
+    fn finish(&mut self, key: Option<&str>, child: Child<'_>, value: Total) {
+        let Some(parent) = self.stack.last_mut() else {
+            self.result = value;
             return;
+        };
+        parent.total += value;
+        for (cond, (any, all)) in self.query.conds.iter().zip(&mut parent.children) {
+            let matches = cond.matches(key, child);
+            *any |= matches;
+            *all &= matches;
         }
+    }
 
-        match value {
-            Null | Bool(..) | String(..) => {}
-            Number(n) => self.total += n.as_i64().unwrap(),
-            Array(arr) => arr.iter().for_each(|v| self.visit(v, reject)),
-            Object(o) => o.values().for_each(|v| self.visit(v, reject)),
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Key(k) => self.stack.last_mut().unwrap().pending_key = Some(k),
+            Event::Start(kind) => {
+                let key = self.stack.last_mut().and_then(|f| f.pending_key.take());
+                self.stack.push(Frame {
+                    kind,
+                    key,
+                    pending_key: None,
+                    total: 0,
+                    children: vec![(false, true); self.query.conds.len()],
+                });
+            }
+            Event::End => {
+                let frame = self.stack.pop().unwrap();
+                let node = Node {
+                    kind: frame.kind,
+                    key: frame.key.as_deref(),
+                    depth: self.stack.len(),
+                    children: &frame.children,
+                };
+                let value = if self.query.accepts(&node) {
+                    frame.total
+                } else {
+                    0
+                };
+                self.finish(frame.key.as_deref(), Child::Container, value);
+            }
+            Event::Scalar(lit) => {
+                let key = self.stack.last_mut().and_then(|f| f.pending_key.take());
+                let kind = match lit {
+                    Literal::Null => Kind::Null,
+                    Literal::Bool(_) => Kind::Bool,
+                    Literal::Num(_) => Kind::Number,
+                    Literal::Str(_) => Kind::String,
+                };
+                let node = Node {
+                    kind,
+                    key: key.as_deref(),
+                    depth: self.stack.len(),
+                    children: &[],
+                };
+                let value = match lit {
+                    Literal::Num(n) if self.query.accepts(&node) => match self.query.aggregate {
+                        Aggregate::Sum => Total::from(n),
+                        Aggregate::Count => 1,
+                    },
+                    _ => 0,
+                };
+                self.finish(key.as_deref(), Child::Scalar(&lit), value);
+            }
         }
     }
 }
 
-fn solve_both(input: &str) -> (i64, i64) {
-    let document = serde_json::from_str::<Json>(input).unwrap();
+/// Feeds a JSON value to the evaluator as events while it is being
+/// deserialized, so no tree is built unless the deserializer has one.
+struct Feed<'e, 'q>(&'e mut Evaluator<'q>);
 
-    let mut adder = Adder::default();
-    adder.visit(&document, &|_| false);
-    let first = adder.total;
+impl<'de> DeserializeSeed<'de> for Feed<'_, '_> {
+    type Value = ();
 
-    let mut adder = Adder::default();
-    let reject_with_red_prop = |v: &Json| {
-        v.as_object()
-            .map_or(false, |o| o.values().any(|prop| prop == "red"))
-    };
-    adder.visit(&document, &reject_with_red_prop);
-    let second = adder.total;
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Feed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("JSON with integer numbers fitting in 64 bits")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.0.event(Event::Scalar(Literal::Null));
+        Ok(())
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<(), E> {
+        self.0.event(Event::Scalar(Literal::Bool(b)));
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<(), E> {
+        self.0.event(Event::Scalar(Literal::Num(n)));
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<(), E> {
+        match i64::try_from(n) {
+            Ok(n) => self.visit_i64(n),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Unsigned(n), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        self.visit_string(s.to_owned())
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<(), E> {
+        self.0.event(Event::Scalar(Literal::Str(s)));
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let evaluator = self.0;
+        evaluator.event(Event::Start(Kind::Array));
+        while seq.next_element_seed(Feed(evaluator))?.is_some() {}
+        evaluator.event(Event::End);
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let evaluator = self.0;
+        evaluator.event(Event::Start(Kind::Object));
+        while let Some(key) = map.next_key::<String>()? {
+            evaluator.event(Event::Key(key));
+            map.next_value_seed(Feed(evaluator))?;
+        }
+        evaluator.event(Event::End);
+        Ok(())
+    }
+}
+
+fn evaluate<'de, D: Deserializer<'de>>(query: &Query, document: D) -> Result<Total, D::Error> {
+    let mut evaluator = Evaluator::new(query);
+    Feed(&mut evaluator).deserialize(document)?;
+    Ok(evaluator.result)
+}
+
+/// Floats and numbers outside `i64` are errors, as they are in `run_reader`.
+fn run(query: &Query, document: &Json) -> serde_json::Result<Total> {
+    evaluate(query, document)
+}
+
+/// Like `run`, but reads the document as it goes, so memory depends only on
+/// how deeply the document is nested.
+fn run_reader(query: &Query, reader: impl Read) -> serde_json::Result<Total> {
+    let mut document = serde_json::Deserializer::from_reader(BufReader::new(reader));
+    let total = evaluate(query, &mut document)?;
+    document.end()?;
+    Ok(total)
+}
+
+const FIRST_QUERY: &str = "sum numbers";
+const SECOND_QUERY: &str = r#"sum numbers where not object.any(value == "red")"#;
 
+fn solve_both(input: &str) -> (Total, Total) {
+    let document = serde_json::from_str::<Json>(input).unwrap();
+    let first = run(&Query::parse(FIRST_QUERY).unwrap(), &document).unwrap();
+    let second = run(&Query::parse(SECOND_QUERY).unwrap(), &document).unwrap();
     (first, second)
 }
+
+#[test]
+fn examples() {
+    let check = |query: &str, input: &str, expected: Total| {
+        let query = Query::parse(query).unwrap();
+        let document = serde_json::from_str::<Json>(input).unwrap();
+        assert_eq!(run(&query, &document).unwrap(), expected, "{input}");
+        assert_eq!(
+            run_reader(&query, input.as_bytes()).unwrap(),
+            expected,
+            "{input}"
+        );
+    };
+
+    for (input, expected) in [
+        ("[1,2,3]", 6),
+        (r#"{"a":2,"b":4}"#, 6),
+        ("[[[3]]]", 3),
+        (r#"{"a":{"b":4},"c":-1}"#, 3),
+        (r#"{"a":[-1,1]}"#, 0),
+        (r#"[-1,{"a":1}]"#, 0),
+        ("[]", 0),
+        ("{}", 0),
+    ] {
+        check(FIRST_QUERY, input, expected);
+    }
+    for (input, expected) in [
+        (r#"[1,{"c":"red","b":2},3]"#, 4),
+        (r#"{"d":"red","e":[1,2,3,4],"f":5}"#, 0),
+        (r#"[1,"red",5]"#, 6),
+    ] {
+        check(SECOND_QUERY, input, expected);
+    }
+    assert_eq!(solve_both(r#"[1,{"c":"red","b":2},3]"#), (6, 4));
+
+    let input = r#" {"a": [1, [2, [3]]], "b": {"c": 4, "d": [5]}, "e": 6, "f": "réd"} "#;
+    check("count numbers", input, 6);
+    check("sum numbers where depth <= 2", input, 1 + 4 + 6);
+    check("sum numbers where not key == \"b\"", input, 1 + 2 + 3 + 6);
+    check("sum numbers where key != \"d\" and not array", input, 4 + 6);
+    check(
+        "sum numbers where not object.any(key == \"c\")",
+        input,
+        1 + 2 + 3 + 6,
+    );
+    check("sum numbers where not array.all(value != 3)", input, 4 + 6);
+    check(
+        "sum numbers where not object.any(value == \"réd\")",
+        input,
+        0,
+    );
+    check(
+        "sum numbers where not number or depth >= 3",
+        input,
+        2 + 3 + 5,
+    );
+    check(
+        "count numbers where not (object.any(value == \"réd\") and depth > 0)",
+        r#"[1, {"x": "r\u00e9d", "y": 2}, {"z": "red\"", "w": 3}]"#,
+        2,
+    );
+}
+
+#[test]
+fn bad_queries() {
+    for query in [
+        "sum",
+        "sum numbers where",
+        "sum numbers where depth <= -1",
+        "sum numbers where number.any(value == 1)",
+        "sum numbers where object.some(value == 1)",
+        "sum numbers where key < \"a\"",
+        "sum numbers where (array",
+        "sum numbers where array or",
+        "product numbers",
+    ] {
+        assert!(Query::parse(query).is_err(), "{query}");
+    }
+
+    let query = Query::parse(FIRST_QUERY).unwrap();
+    assert!(run_reader(&query, &b"[1, 2"[..]).is_err());
+    assert!(run_reader(&query, &b"[1, 2}"[..]).is_err());
+    assert!(run_reader(&query, &b"[1, two]"[..]).is_err());
+    for input in [
+        "[1 2]",
+        r#"{"a" 1}"#,
+        "{1:2}",
+        r#"{"a":1 "b":2}"#,
+        r#"{"a":1,}"#,
+        "[1,]",
+        "[,1]",
+        "[1]]",
+        "[1] [2]",
+        r#"["a":1]"#,
+        r#"{"a"}"#,
+    ] {
+        assert!(run_reader(&query, input.as_bytes()).is_err(), "{input}");
+    }
+    assert!(run_reader(&query, &br#"["\ud800\u0041", 1]"#[..]).is_err());
+
+    // both paths reject numbers the queries cannot hold
+    for input in ["[1.5]", "[18446744073709551615]", "[1e400]"] {
+        assert!(run_reader(&query, input.as_bytes()).is_err(), "{input}");
+        if let Ok(document) = serde_json::from_str::<Json>(input) {
+            assert!(run(&query, &document).is_err(), "{input}");
+        }
+    }
+}
+
+#[test]
+fn wide_totals() {
+    let input = format!("[{max}, {{\"a\": [{max}, 2]}}]", max = i64::MAX);
+    let expected = 2 * Total::from(i64::MAX) + 2;
+    let query = Query::parse(FIRST_QUERY).unwrap();
+    assert_eq!(run_reader(&query, input.as_bytes()).unwrap(), expected);
+    assert_eq!(solve_both(&input), (expected, expected));
+}
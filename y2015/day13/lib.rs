@@ -24,8 +24,46 @@ impl<'i> Interner<'i> {
 
 type Happiness = i32;
 
-fn parse_line(s: &str) -> (&str, &str, Happiness) {
-    let (first, rest) = s.split_once(" would ").unwrap();
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Constraint<G> {
+    /// The two guests must not sit next to each other.
+    Apart(G, G),
+    /// The guest sits at this seat, seats are numbered around the table.
+    Seat(G, usize),
+    /// The guests sit next to each other, in any order.
+    Block(Vec<G>),
+}
+
+impl<'a> Constraint<&'a str> {
+    /// One of
+    /// * `Alice must not sit next to Bob.`
+    /// * `Alice sits at seat 0.`
+    /// * `Alice, Bob and Carol sit together.`
+    fn parse(s: &'a str) -> Option<Self> {
+        let s = s.strip_suffix('.')?;
+        if let Some((a, b)) = s.split_once(" must not sit next to ") {
+            return Some(Self::Apart(a, b));
+        }
+        if let Some((guest, seat)) = s.split_once(" sits at seat ") {
+            return Some(Self::Seat(guest, seat.parse().ok()?));
+        }
+        let guests = s.strip_suffix(" sit together")?;
+        let (init, last) = guests.rsplit_once(" and ")?;
+        Some(Self::Block(init.split(", ").chain([last]).collect()))
+    }
+}
+
+enum Line<'a> {
+    Delta(&'a str, &'a str, Happiness),
+    Constraint(Constraint<&'a str>),
+}
+
+fn parse_line(s: &str) -> Line<'_> {
+    let Some((first, rest)) = s.split_once(" would ") else {
+        return Line::Constraint(
+            Constraint::parse(s).unwrap_or_else(|| panic!("invalid string {s}")),
+        );
+    };
     let (multiplier, rest) = if let Some(rest) = rest.strip_prefix("gain ") {
         (1, rest)
     } else if let Some(rest) = rest.strip_prefix("lose ") {
@@ -37,58 +75,337 @@ fn parse_line(s: &str) -> (&str, &str, Happiness) {
         .split_once(" happiness units by sitting next to ")
         .unwrap();
     let second = second_dot.strip_suffix(".").unwrap();
-    (
+    Line::Delta(
         first,
         second,
         amount.parse::<Happiness>().unwrap() * multiplier,
     )
 }
 
-fn parse_deltas(s: &str) -> Vec<Vec<Happiness>> {
-    let mut int = Interner::default();
-    let deltas = s
-        .lines()
-        .filter(|l| !l.is_empty())
-        .map(parse_line)
-        .map(|(fst, snd, delta)| (int.insert(fst), int.insert(snd), delta))
-        .collect::<Vec<_>>();
-    let len = deltas.iter().map(|x| x.0).max().unwrap() + 1;
-    let mut ret = vec![vec![0; len]; len];
-    for &(i, j, delta) in &deltas {
-        ret[i][j] = delta;
+struct Table<'a> {
+    guests: Interner<'a>,
+    /// `deltas[i][j]` is how `i` feels about sitting next to `j`.
+    deltas: Vec<Vec<Happiness>>,
+    constraints: Vec<Constraint<usize>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Arrangement<'a> {
+    happiness: Happiness,
+    /// Guests around the table, starting from seat 0.
+    guests: Vec<&'a str>,
+}
+
+/// Entry of a per-state top list: the score and where it came from.
+#[derive(Clone, Copy)]
+struct Partial {
+    happiness: Happiness,
+    prev: usize,
+    prev_rank: usize,
+}
+
+fn insert_top(list: &mut Vec<Partial>, k: usize, entry: Partial) {
+    let pos = list.partition_point(|p| p.happiness >= entry.happiness);
+    if pos < k {
+        list.insert(pos, entry);
+        list.truncate(k);
     }
-    ret
-}
-
-fn best_arrangement(deltas: &[Vec<Happiness>]) -> Happiness {
-    let n_guests = deltas.len();
-    (0..n_guests)
-        .permutations(n_guests)
-        .map(|p| {
-            p.iter()
-                .cycle()
-                .tuple_windows()
-                .take(n_guests)
-                .map(|(&i, &j)| deltas[i][j] + deltas[j][i])
-                .sum()
+}
+
+impl<'a> Table<'a> {
+    /// Happiness lines, optionally followed by constraint lines.
+    fn parse(s: &'a str) -> Self {
+        let mut guests = Interner::default();
+        let mut deltas = Vec::new();
+        let mut constraints = Vec::new();
+        for line in s.lines().filter(|l| !l.is_empty()).map(parse_line) {
+            match line {
+                Line::Delta(fst, snd, delta) => {
+                    assert!(constraints.is_empty(), "happiness after constraints");
+                    deltas.push((guests.insert(fst), guests.insert(snd), delta));
+                }
+                Line::Constraint(c) => constraints.push(c),
+            }
+        }
+
+        let len = guests.strs.len();
+        let mut ret = Self {
+            guests,
+            deltas: vec![vec![0; len]; len],
+            constraints: Vec::new(),
+        };
+        for (i, j, delta) in deltas {
+            ret.deltas[i][j] = delta;
+        }
+        for c in constraints {
+            ret.add_constraint(c).unwrap();
+        }
+        ret
+    }
+
+    fn add_constraint(&mut self, constraint: Constraint<&str>) -> Result<(), String> {
+        let guest = |name: &str| {
+            self.guests
+                .bag
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("{name} is not invited"))
+        };
+        let constraint = match constraint {
+            Constraint::Apart(a, b) => Constraint::Apart(guest(a)?, guest(b)?),
+            Constraint::Seat(g, seat) => Constraint::Seat(guest(g)?, seat),
+            Constraint::Block(gs) => {
+                Constraint::Block(gs.into_iter().map(guest).collect::<Result<_, _>>()?)
+            }
+        };
+        self.constraints.push(constraint);
+        Ok(())
+    }
+
+    /// Adds a guest who feels `delta` about every neighbour and makes every
+    /// neighbour feel the same about them. The puzzle's "yourself" is a
+    /// neutral guest with `delta` of 0.
+    fn add_guest(&mut self, name: &'a str, delta: Happiness) {
+        let len = self.deltas.len();
+        assert_eq!(self.guests.insert(name), len, "{name} is already invited");
+        self.deltas.iter_mut().for_each(|dd| dd.push(delta));
+        self.deltas.push(vec![delta; len + 1]);
+        self.deltas[len][len] = 0;
+    }
+
+    fn pair(&self, i: usize, j: usize) -> Happiness {
+        self.deltas[i][j] + self.deltas[j][i]
+    }
+
+    /// The `k` happiest arrangements satisfying the constraints, best
+    /// first; empty if there are none. One guest stays at the first fixed
+    /// seat (or seat 0) so rotations are not counted, and unless two seats
+    /// are fixed reflections are dropped by seating the two lowest other
+    /// guests in order.
+    ///
+    /// Bitmask DP: `tops[seated][last]` holds the best partial arrangements
+    /// which seat the `seated` set of guests in some order ending with
+    /// `last`, so the whole thing takes O(2^n·n²·k) time.
+    fn top_arrangements(&self, k: usize) -> Vec<Arrangement<'a>> {
+        let n = self.deltas.len();
+        if n == 0 || k == 0 {
+            return Vec::new();
+        }
+        let Some(rules) = Rules::new(n, &self.constraints) else {
+            return Vec::new();
+        };
+
+        let idx = |seated: usize, last: usize| seated * n + last;
+        let mut tops = vec![Vec::new(); n << n];
+        tops[idx(1 << rules.start, rules.start)].push(Partial {
+            happiness: 0,
+            prev: rules.start,
+            prev_rank: 0,
+        });
+
+        for seated in (1..1 << n).filter(|seated| seated & 1 << rules.start != 0) {
+            for last in 0..n {
+                if tops[idx(seated, last)].is_empty() {
+                    continue;
+                }
+                for next in (0..n).filter(|&g| rules.allows(seated, last, g)) {
+                    for rank in 0..tops[idx(seated, last)].len() {
+                        let entry = Partial {
+                            happiness: tops[idx(seated, last)][rank].happiness
+                                + self.pair(last, next),
+                            prev: last,
+                            prev_rank: rank,
+                        };
+                        insert_top(&mut tops[idx(seated | 1 << next, next)], k, entry);
+                    }
+                }
+            }
+        }
+
+        let all = (1 << n) - 1;
+        let mut finals = Vec::new();
+        for last in (0..n).filter(|&g| n == 1 || !rules.apart[g][rules.start]) {
+            for (rank, partial) in tops[idx(all, last)].iter().enumerate() {
+                let entry = Partial {
+                    happiness: partial.happiness + self.pair(last, rules.start),
+                    prev: last,
+                    prev_rank: rank,
+                };
+                insert_top(&mut finals, k, entry);
+            }
+        }
+
+        finals
+            .into_iter()
+            .map(|end| {
+                let mut guests = Vec::with_capacity(n);
+                let (mut seated, mut last, mut rank) = (all, end.prev, end.prev_rank);
+                while seated != 0 {
+                    guests.push(self.guests.strs[last]);
+                    let partial = tops[idx(seated, last)][rank];
+                    seated &= !(1 << last);
+                    (last, rank) = (partial.prev, partial.prev_rank);
+                }
+                guests.reverse();
+                guests.rotate_right(rules.offset);
+                Arrangement {
+                    happiness: end.happiness,
+                    guests,
+                }
+            })
+            .collect()
+    }
+
+    fn best_arrangement(&self) -> Arrangement<'a> {
+        self.top_arrangements(1).pop().unwrap()
+    }
+}
+
+/// Constraints prepared for the seating DP, which places guests one by one
+/// starting from `start`.
+struct Rules {
+    start: usize,
+    /// Seat of `start`, positions in the DP are relative to it.
+    offset: usize,
+    apart: Vec<Vec<bool>>,
+    /// Position of guests with a fixed seat and the other way round.
+    position: Vec<Option<usize>>,
+    seated_at: Vec<Option<usize>>,
+    /// Masks of guests which sit together.
+    blocks: Vec<usize>,
+    /// `(a, b)` such that `b` is only seated after `a`.
+    order: Option<(usize, usize)>,
+}
+
+impl Rules {
+    /// `None` if the constraints contradict each other.
+    fn new(n: usize, constraints: &[Constraint<usize>]) -> Option<Self> {
+        let mut apart = vec![vec![false; n]; n];
+        let mut seats = Vec::new();
+        let mut blocks = Vec::new();
+        for c in constraints {
+            match c {
+                &Constraint::Apart(a, b) => {
+                    apart[a][b] = true;
+                    apart[b][a] = true;
+                }
+                &Constraint::Seat(g, seat) => seats.push((g, seat)),
+                Constraint::Block(gs) => blocks.push(gs.iter().fold(0, |m, g| m | 1 << g)),
+            }
+        }
+
+        let (start, offset) = seats
+            .iter()
+            .copied()
+            .min_by_key(|&(_, seat)| seat)
+            .unwrap_or((0, 0));
+        let mut position = vec![None; n];
+        let mut seated_at = vec![None; n];
+        for &(g, seat) in &seats {
+            if seat >= n {
+                return None;
+            }
+            let pos = seat - offset;
+            if *position[g].get_or_insert(pos) != pos || *seated_at[pos].get_or_insert(g) != g {
+                return None;
+            }
+        }
+
+        let n_fixed = seated_at.iter().flatten().count();
+        let mut others = (0..n).filter(|&g| g != start);
+        let order = match (others.next(), others.next()) {
+            (Some(a), Some(b)) if n_fixed < 2 => Some((a, b)),
+            _ => None,
+        };
+
+        Some(Self {
+            start,
+            offset,
+            apart,
+            position,
+            seated_at,
+            blocks,
+            order,
+        })
+    }
+
+    /// Whether `next` may sit right after `last`, with the `seated` guests
+    /// already around the table.
+    fn allows(&self, seated: usize, last: usize, next: usize) -> bool {
+        let pos = seated.count_ones() as usize;
+        if seated & 1 << next != 0 || self.apart[last][next] {
+            return false;
+        }
+        if self.position[next].is_some_and(|p| p != pos)
+            || self.seated_at[pos].is_some_and(|g| g != next)
+        {
+            return false;
+        }
+        if let Some((a, b)) = self.order {
+            if next == b && seated & 1 << a == 0 {
+                return false;
+            }
+        }
+
+        let all = (1 << self.position.len()) - 1;
+        self.blocks.iter().all(|&block| {
+            let (in_last, in_next) = (block & 1 << last != 0, block & 1 << next != 0);
+            let started = seated & block != 0;
+            let complete = seated & block == block;
+            if block & 1 << self.start == 0 {
+                // one run somewhere in the middle
+                match (in_last, in_next) {
+                    (_, true) => !started || in_last,
+                    (true, false) => complete,
+                    (false, false) => true,
+                }
+            } else {
+                // a run from seat 0 and possibly another one wrapping around
+                // to it from the end, which may only start once everybody
+                // else is seated
+                match (in_last, in_next) {
+                    (false, true) => seated & !block == all & !block,
+                    _ => true,
+                }
+            }
         })
-        .max()
-        .unwrap()
+    }
 }
 
 fn solve_both(input: &str) -> (Happiness, Happiness) {
-    let mut deltas = parse_deltas(input);
-    let n_guests = deltas.len();
-    let first = best_arrangement(&deltas);
-    deltas.iter_mut().for_each(|dd| dd.push(0));
-    deltas.push(vec![0; n_guests + 1]);
-    let second = best_arrangement(&deltas);
+    let mut table = Table::parse(input);
+    let first = table.best_arrangement().happiness;
+    table.add_guest("Yourself", 0);
+    let second = table.best_arrangement().happiness;
     (first, second)
 }
 
 #[test]
 fn example() {
     assert_eq!(solve_both(INPUT), (330, 286));
+
+    let table = Table::parse(INPUT);
+    let top = table.top_arrangements(4);
+    assert_eq!(top[0].guests, ["Alice", "Bob", "Carol", "David"]);
+    // 4 guests sit in 3 different ways up to rotation and reflection
+    assert_eq!(
+        top.iter().map(|a| a.happiness).collect::<Vec<_>>(),
+        [330, 22, -114]
+    );
+    assert_eq!(top[2].guests, ["Alice", "David", "Bob", "Carol"]);
+}
+
+#[test]
+fn add_guests() {
+    let mut table = Table::parse(INPUT);
+    table.add_guest("Yourself", 0);
+    table.add_guest("Eve", -100);
+    let best = table.best_arrangement();
+    assert_eq!(best.happiness, 286 - 400);
+    assert_eq!(best.guests.len(), 6);
+    let eve = best.guests.iter().position(|&g| g == "Eve").unwrap();
+    let across = [(eve + 1) % 6, (eve + 5) % 6].map(|i| best.guests[i]);
+    assert!(across.contains(&"Yourself"));
 }
 
 const INPUT: &str = "
@@ -105,3 +422,116 @@ David would gain 46 happiness units by sitting next to Alice.
 David would lose 7 happiness units by sitting next to Bob.
 David would gain 41 happiness units by sitting next to Carol.
 ";
+
+#[test]
+fn constraints() {
+    let with = |extra: &str| format!("{INPUT}{extra}");
+    fn best(input: &str) -> Option<Arrangement<'_>> {
+        Table::parse(input).top_arrangements(1).pop()
+    }
+
+    let input = with("Alice must not sit next to Bob.\n");
+    assert_eq!(best(&input).unwrap().happiness, -114);
+    let input = with("Bob sits at seat 2.\n");
+    let seated = best(&input).unwrap();
+    assert_eq!(seated.guests, ["David", "Carol", "Bob", "Alice"]);
+    assert_eq!(seated.happiness, 330);
+    assert_eq!(best(&with("Alice sits at seat 4.\n")), None);
+    assert_eq!(
+        best(&with("Alice sits at seat 1.\nBob sits at seat 1.\n")),
+        None
+    );
+    assert_eq!(
+        Table::parse(INPUT).add_constraint(Constraint::Apart("Alice", "Eve")),
+        Err("Eve is not invited".to_owned())
+    );
+
+    // compare against trying every permutation
+    let mut table = Table::parse(INPUT);
+    table.add_guest("Yourself", 0);
+    table.add_guest("Eve", 5);
+    let sets = [
+        vec![Constraint::Block(vec!["Bob", "David", "Eve"])],
+        vec![Constraint::Block(vec!["Alice", "Eve"])],
+        vec![
+            Constraint::Block(vec!["Alice", "Carol", "Yourself"]),
+            Constraint::Apart("Yourself", "Alice"),
+        ],
+        vec![
+            Constraint::Seat("Eve", 1),
+            Constraint::Seat("Carol", 4),
+            Constraint::Apart("Bob", "David"),
+        ],
+        vec![
+            Constraint::Seat("Alice", 5),
+            Constraint::Block(vec!["Bob", "Carol"]),
+            Constraint::Block(vec!["Alice", "Carol"]),
+        ],
+        vec![
+            Constraint::Seat("Alice", 0),
+            Constraint::Seat("Bob", 3),
+            Constraint::Block(vec!["Alice", "Bob"]),
+        ],
+    ];
+    for constraints in sets {
+        let mut constrained = Table {
+            guests: Interner::default(),
+            deltas: table.deltas.clone(),
+            constraints: Vec::new(),
+        };
+        table
+            .guests
+            .strs
+            .iter()
+            .for_each(|g| _ = constrained.guests.insert(g));
+        for c in &constraints {
+            constrained.add_constraint(c.clone()).unwrap();
+        }
+        let found = constrained.top_arrangements(1).pop();
+
+        let n = table.deltas.len();
+        let satisfies = |p: &[usize]| {
+            let next_to = |a, b| {
+                let (pa, pb) = (
+                    p.iter().position(|&g| g == a),
+                    p.iter().position(|&g| g == b),
+                );
+                let d = pa.unwrap().abs_diff(pb.unwrap());
+                d == 1 || d == n - 1
+            };
+            constrained.constraints.iter().all(|c| match c {
+                &Constraint::Apart(a, b) => !next_to(a, b),
+                &Constraint::Seat(g, seat) => p[seat] == g,
+                Constraint::Block(gs) => (0..n).any(|from| {
+                    let run = (from..from + gs.len())
+                        .map(|i| p[i % n])
+                        .collect::<Vec<_>>();
+                    gs.iter().all(|g| run.contains(g))
+                }),
+            })
+        };
+        let expected = (0..n)
+            .permutations(n)
+            .filter(|p| satisfies(p))
+            .map(|p| {
+                (0..n)
+                    .map(|i| table.pair(p[i], p[(i + 1) % n]))
+                    .sum::<Happiness>()
+            })
+            .max();
+        assert_eq!(
+            found.as_ref().map(|a| a.happiness),
+            expected,
+            "{constraints:?}"
+        );
+
+        if let Some(found) = found {
+            let seats = found
+                .guests
+                .iter()
+                .map(|g| table.guests.bag[g])
+                .collect::<Vec<_>>();
+            assert!(satisfies(&seats), "{constraints:?}: {found:?}");
+        }
+    }
+}
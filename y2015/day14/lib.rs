@@ -1,5 +1,10 @@
 #![cfg(test)]
 
+#[path = "../../common/trace.rs"]
+mod trace;
+
+use trace::{NoTrace, Tracer};
+
 type Num = u32;
 type Speed = Num;
 type Time = Num;
@@ -7,19 +12,21 @@ type Distance = Num;
 
 type Score = u32;
 
-struct Reindeer {
+struct Reindeer<'a> {
+    name: &'a str,
     speed: Speed,
     time_fly: Time,
     time_rest: Time,
 }
 
-impl Reindeer {
-    fn parse(s: &str) -> Self {
-        let (_name, rest) = s.split_once(" can fly ").unwrap();
+impl<'a> Reindeer<'a> {
+    fn parse(s: &'a str) -> Self {
+        let (name, rest) = s.split_once(" can fly ").unwrap();
         let (speed, rest) = rest.split_once(" km/s for ").unwrap();
         let (time_fly, rest) = rest.split_once(" seconds, but then must rest for ").unwrap();
         let time_rest = rest.strip_suffix(" seconds.").unwrap();
         Self {
+            name,
             speed: speed.parse().unwrap(),
             time_fly: time_fly.parse().unwrap(),
             time_rest: time_rest.parse().unwrap(),
@@ -34,17 +41,38 @@ impl Reindeer {
         let additional_reach = self.time_fly.min(additional_time) * self.speed;
         reach_per_cycle * whole_cycles + additional_reach
     }
-    
-    fn kick(self) -> MovingReindeer {
-        assert_ne!(self.time_fly, 0);
-        assert_ne!(self.time_rest, 0);
-
-        let time_fly = self.time_fly;
-        MovingReindeer {
-            reindeer: self,
-            state: State::Flying(time_fly),
-            pos: 0,
+
+    fn cycle(&self) -> Time {
+        self.time_fly + self.time_rest
+    }
+
+    fn is_flying(&self, time: Time) -> bool {
+        time % self.cycle() < self.time_fly
+    }
+
+    /// What the reindeer starts doing at `time`, if anything. One which
+    /// never rests starts flying once and keeps at it.
+    fn starts(&self, time: Time) -> Option<&'static str> {
+        match time % self.cycle() {
+            0 if time == 0 || self.time_rest != 0 => Some("fly"),
+            phase if phase == self.time_fly => Some("rest"),
+            _ => None,
+        }
+    }
+
+    /// First moment the reindeer is at least `distance` away, `None` if it
+    /// never moves.
+    fn first_reach(&self, distance: Distance) -> Option<Time> {
+        if distance == 0 {
+            return Some(0);
         }
+        let reach_per_cycle = self.speed * self.time_fly;
+        if reach_per_cycle == 0 {
+            return None;
+        }
+        let whole_cycles = (distance - 1) / reach_per_cycle;
+        let additional_reach = distance - whole_cycles * reach_per_cycle;
+        Some(whole_cycles * self.cycle() + additional_reach.div_ceil(self.speed))
     }
 }
 
@@ -52,51 +80,255 @@ fn winner_distance(reindeers: &[Reindeer], total_time: Time) -> Distance {
     reindeers.iter().map(|r| r.reach(total_time)).max().unwrap_or(0)
 }
 
-enum State {
-    Flying(Time),
-    Resting(Time),
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Ties {
+    /// Everybody tied for the point gets it.
+    Shared,
+    /// A tied point goes to nobody.
+    Nobody,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Scoring {
+    /// A point per second for being in the lead, as in the puzzle.
+    Leader(Ties),
+    /// A point for being the first to get `every` km further.
+    Milestones { every: Distance, ties: Ties },
+}
+
+/// Every listed reindeer gets a point per second from `from` to `to`,
+/// both inclusive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Award {
+    from: Time,
+    to: Time,
+    reindeer: Vec<usize>,
 }
 
-struct MovingReindeer {
-    reindeer: Reindeer,
-    state: State,
-    pos: Distance,
+struct Race<'r, 'a> {
+    reindeer: &'r [Reindeer<'a>],
+    time: Time,
+    /// Moments when some reindeer starts or stops flying.
+    phase_changes: Vec<Time>,
+    /// Sorted by `from`.
+    awards: Vec<Award>,
 }
 
-impl MovingReindeer {
-    fn tick(&mut self) -> Distance {
-        match &mut self.state {
-            State::Flying(time_left) => {
-                self.pos += self.reindeer.speed;
-                *time_left -= 1;
-                if *time_left == 0 {
-                    self.state = State::Resting(self.reindeer.time_rest);
+fn winners(candidates: Vec<usize>, ties: Ties) -> Vec<usize> {
+    match ties {
+        Ties::Nobody if candidates.len() > 1 => Vec::new(),
+        _ => candidates,
+    }
+}
+
+impl<'r, 'a> Race<'r, 'a> {
+    /// Runs the race from phase change to phase change. In between every
+    /// reindeer moves at a constant speed, so the lead can only change
+    /// around the moments when two of them meet.
+    fn run(
+        reindeer: &'r [Reindeer<'a>],
+        time: Time,
+        scoring: Scoring,
+        tracer: &mut impl Tracer,
+    ) -> Self {
+        let mut phase_changes = Vec::new();
+        for r in reindeer {
+            assert_ne!(r.time_fly, 0);
+            if r.time_rest == 0 {
+                phase_changes.push(0);
+                continue;
+            }
+            let mut start = 0;
+            while start < time {
+                phase_changes.push(start);
+                phase_changes.push(start + r.time_fly);
+                start += r.cycle();
+            }
+        }
+        phase_changes.retain(|&t| t < time);
+        phase_changes.sort_unstable();
+        phase_changes.dedup();
+
+        for &t in &phase_changes {
+            for r in reindeer {
+                let Some(kind) = r.starts(t) else {
+                    continue;
+                };
+                tracer.emit(
+                    kind,
+                    &[
+                        ("name", r.name.into()),
+                        ("time", t.into()),
+                        ("pos", r.reach(t).into()),
+                    ],
+                );
+            }
+        }
+
+        let mut ret = Self {
+            reindeer,
+            time,
+            phase_changes,
+            awards: Vec::new(),
+        };
+        match scoring {
+            Scoring::Leader(ties) => ret.score_leaders(ties),
+            Scoring::Milestones { every, ties } => ret.score_milestones(every, ties),
+        }
+        ret
+    }
+
+    fn push_award(&mut self, from: Time, to: Time, reindeer: Vec<usize>) {
+        if let Some(last) = self.awards.last_mut() {
+            if last.to + 1 == from && last.reindeer == reindeer {
+                last.to = to;
+                return;
+            }
+        }
+        self.awards.push(Award { from, to, reindeer });
+    }
+
+    fn score_leaders(&mut self, ties: Ties) {
+        let bounds = self
+            .phase_changes
+            .iter()
+            .copied()
+            .chain([self.time])
+            .collect::<Vec<_>>();
+        for window in bounds.windows(2) {
+            let (start, end) = (window[0], window[1]);
+            let pos = self
+                .reindeer
+                .iter()
+                .map(|r| i64::from(r.reach(start)))
+                .collect::<Vec<_>>();
+            let speed = self
+                .reindeer
+                .iter()
+                .map(|r| {
+                    if r.is_flying(start) {
+                        i64::from(r.speed)
+                    } else {
+                        0
+                    }
+                })
+                .collect::<Vec<_>>();
+            let at = |i: usize, t: Time| pos[i] + speed[i] * i64::from(t - start);
+
+            // seconds around every meeting point, the order of reindeer is
+            // the same for all seconds in between
+            let mut critical = vec![end];
+            for i in 0..pos.len() {
+                for j in i + 1..pos.len() {
+                    let (gap, closing) = (pos[j] - pos[i], speed[i] - speed[j]);
+                    if closing == 0 || gap.signum() != closing.signum() {
+                        continue;
+                    }
+                    let (floor, ceil) =
+                        (gap / closing, (gap + closing - closing.signum()) / closing);
+                    for offset in [floor, ceil] {
+                        let t = i64::from(start) + offset;
+                        if t > i64::from(start) && t < i64::from(end) {
+                            critical.push(t as Time);
+                        }
+                    }
                 }
             }
-            State::Resting(time_left) => {
-                *time_left -= 1;
-                if *time_left == 0 {
-                    self.state = State::Flying(self.reindeer.time_fly);
+            critical.sort_unstable();
+            critical.dedup();
+
+            let leaders = |t: Time| {
+                let best = (0..pos.len()).map(|i| at(i, t)).max().unwrap();
+                winners((0..pos.len()).filter(|&i| at(i, t) == best).collect(), ties)
+            };
+            let mut prev = start;
+            for c in critical {
+                if c > prev + 1 {
+                    self.push_award(prev + 1, c - 1, leaders(prev + 1));
                 }
+                self.push_award(c, c, leaders(c));
+                prev = c;
             }
         }
-        self.pos
+    }
+
+    fn score_milestones(&mut self, every: Distance, ties: Ties) {
+        assert_ne!(every, 0);
+        let furthest = winner_distance(self.reindeer, self.time);
+        for milestone in (every..=furthest).step_by(every as usize) {
+            let times = self
+                .reindeer
+                .iter()
+                .map(|r| r.first_reach(milestone))
+                .collect::<Vec<_>>();
+            let first = times.iter().flatten().min().copied();
+            let first = first.expect("the furthest reindeer gets there");
+            let reached = (0..times.len())
+                .filter(|&i| times[i] == Some(first))
+                .collect();
+            self.awards.push(Award {
+                from: first,
+                to: first,
+                reindeer: winners(reached, ties),
+            });
+        }
+    }
+
+    /// Scores right after second `time`.
+    fn scores(&self, time: Time) -> Vec<Score> {
+        let mut ret = vec![0; self.reindeer.len()];
+        for award in self.awards.iter().take_while(|a| a.from <= time) {
+            for &r in &award.reindeer {
+                ret[r] += award.to.min(time) - award.from + 1;
+            }
+        }
+        ret
+    }
+
+    /// Leaderboard at the start, whenever somebody starts or stops flying,
+    /// whenever the points start going to somebody else, and at the end.
+    fn to_csv(&self) -> String {
+        use std::fmt::Write;
+
+        let mut times = self
+            .phase_changes
+            .iter()
+            .copied()
+            .chain(self.awards.iter().map(|a| a.from))
+            .chain([0, self.time])
+            .collect::<Vec<_>>();
+        times.sort_unstable();
+        times.dedup();
+
+        let mut ret = String::from("time,reindeer,distance,score\n");
+        for time in times {
+            for (r, score) in self.reindeer.iter().zip(self.scores(time)) {
+                _ = writeln!(ret, "{time},{},{},{score}", r.name, r.reach(time));
+            }
+        }
+        ret
     }
 }
 
+fn parse_reindeers(input: &str) -> Vec<Reindeer<'_>> {
+    input
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(Reindeer::parse)
+        .collect()
+}
+
 fn solve_both(input: &str, race_time: Time) -> (Distance, Score) {
-    let reindeers = input.lines().filter(|l| !l.is_empty()).map(Reindeer::parse).collect::<Vec<_>>();
+    solve_both_traced(input, race_time, &mut NoTrace)
+}
+
+fn solve_both_traced(input: &str, race_time: Time, tracer: &mut impl Tracer) -> (Distance, Score) {
+    let reindeers = parse_reindeers(input);
     assert!(!reindeers.is_empty());
 
     let first = winner_distance(&reindeers, race_time);
-
-    let mut reindeers = reindeers.into_iter().map(|r| (r.kick(), 0)).collect::<Vec<_>>();
-    for _ in 0..race_time {
-        let furthest = reindeers.iter_mut().map(|(r, _score)| r.tick()).max().unwrap();
-        reindeers.iter_mut().for_each(|(r, score)| if r.pos == furthest { *score += 1 });
-    }
-    
-    let second = reindeers.iter().map(|(_r, score)| *score).max().unwrap();
+    let race = Race::run(&reindeers, race_time, Scoring::Leader(Ties::Shared), tracer);
+    let second = race.scores(race_time).into_iter().max().unwrap();
 
     (first, second)
 }
@@ -106,6 +338,108 @@ fn example() {
     assert_eq!(solve_both(INPUT, 1000), (1120, 689));
 }
 
+#[test]
+fn trace_rest() {
+    use trace::{Format, Printer};
+
+    let mut tracer = Printer::new(Vec::new(), Some(vec!["rest".into()]), Format::Text);
+    solve_both_traced(INPUT, 150, &mut tracer);
+    let out = String::from_utf8(tracer.into_inner()).unwrap();
+    assert_eq!(
+        out,
+        "rest name=Comet time=10 pos=140\nrest name=Dancer time=11 pos=176\nrest name=Comet time=147 pos=280\n"
+    );
+}
+
+#[test]
+fn never_rests() {
+    use trace::{Format, Printer};
+
+    let input = "Prancer can fly 1 km/s for 1 seconds, but then must rest for 0 seconds.";
+    assert_eq!(solve_both(input, 2503), (2503, 2503));
+    let mut tracer = Printer::new(Vec::new(), None, Format::Text);
+    solve_both_traced(input, 10, &mut tracer);
+    let out = String::from_utf8(tracer.into_inner()).unwrap();
+    assert_eq!(out, "fly name=Prancer time=0 pos=0\n");
+}
+
+#[test]
+fn scoring_rules() {
+    let reindeers = parse_reindeers(INPUT3);
+    assert_eq!(reindeers[4].first_reach(0), Some(0));
+    assert_eq!(reindeers[4].first_reach(1), None);
+    assert_eq!(reindeers[0].first_reach(141), Some(138));
+    let race_time = 2503;
+    for scoring in [
+        Scoring::Leader(Ties::Shared),
+        Scoring::Leader(Ties::Nobody),
+        Scoring::Milestones {
+            every: 100,
+            ties: Ties::Shared,
+        },
+        Scoring::Milestones {
+            every: 7,
+            ties: Ties::Nobody,
+        },
+    ] {
+        // second by second
+        let mut expected = vec![0; reindeers.len()];
+        let mut milestone = 0;
+        for t in 1..=race_time {
+            let pos = reindeers.iter().map(|r| r.reach(t)).collect::<Vec<_>>();
+            let best = *pos.iter().max().unwrap();
+            let mut points = |ties, at_least| {
+                let leaders = (0..pos.len()).filter(|&i| pos[i] >= at_least).collect();
+                winners(leaders, ties)
+                    .into_iter()
+                    .for_each(|i| expected[i] += 1);
+            };
+            match scoring {
+                Scoring::Leader(ties) => points(ties, best),
+                Scoring::Milestones { every, ties } => {
+                    while best >= milestone + every {
+                        milestone += every;
+                        points(ties, milestone);
+                    }
+                }
+            }
+        }
+
+        let race = Race::run(&reindeers, race_time, scoring, &mut NoTrace);
+        assert_eq!(race.scores(race_time), expected, "{scoring:?}");
+    }
+}
+
+#[test]
+fn leaderboard_csv() {
+    let reindeers = parse_reindeers(INPUT);
+    let race = Race::run(&reindeers, 12, Scoring::Leader(Ties::Shared), &mut NoTrace);
+    assert_eq!(
+        race.to_csv(),
+        "time,reindeer,distance,score
+0,Comet,0,0
+0,Dancer,0,0
+1,Comet,14,0
+1,Dancer,16,1
+10,Comet,140,0
+10,Dancer,160,10
+11,Comet,140,0
+11,Dancer,176,11
+12,Comet,140,0
+12,Dancer,176,12
+"
+    );
+}
+
+const INPUT3: &str = "
+Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.
+Vixen can fly 8 km/s for 8 seconds, but then must rest for 53 seconds.
+Blitzen can fly 13 km/s for 4 seconds, but then must rest for 49 seconds.
+Rudolph can fly 0 km/s for 10 seconds, but then must rest for 5 seconds.
+Prancer can fly 1 km/s for 3 seconds, but then must rest for 0 seconds.
+";
+
 const INPUT: &str = "
 Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
 Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.
@@ -1,6 +1,22 @@
 #![cfg(test)]
 
-type Quality = i32;
+#[path = "../../common/memo.rs"]
+mod memo;
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
+
+use memo::Memo;
+
+// a thousand spoons of a 5-point ingredient already put the product of
+// four qualities past i32
+#[cfg(not(any(aoc_checked, aoc_wide)))]
+type Quality = i64;
+#[cfg(aoc_checked)]
+type Quality = numeric::Checked<i64>;
+#[cfg(aoc_wide)]
+type Quality = numeric::Checked<i128>;
+type Spoons = i32;
 
 const N_QUALITIES: usize = 4;
 
@@ -23,16 +39,24 @@ impl Ingredient {
             calories,
         }
     }
+
+    fn get(&self, property: Property) -> Quality {
+        match property {
+            Property::Quality(i) => self.qualities[i],
+            Property::Calories => self.calories,
+        }
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, PartialEq, Eq)]
 struct Cookie {
     qualities: [Quality; N_QUALITIES],
     calories: Quality,
 }
 
 impl Cookie {
-    fn add(&self, amount: Quality, ingredient: &Ingredient) -> Self {
+    fn add(&self, amount: Spoons, ingredient: &Ingredient) -> Self {
+        let amount = Quality::from(amount);
         let mut qualities = self.qualities;
         qualities
             .iter_mut()
@@ -46,58 +70,352 @@ impl Cookie {
     }
 
     fn score(&self) -> Quality {
-        self.qualities.iter().map(|&q| q.max(0)).product()
+        self.qualities
+            .iter()
+            .map(|&q| q.max(Quality::from(0)))
+            .product()
     }
 }
 
-fn choose_best(
-    ingredients: &[Ingredient],
-    total_spoons: Quality,
-    allow: impl Fn(&Cookie) -> bool,
-) -> Option<Quality> {
-    choose_best_recursive(Cookie::default(), ingredients, total_spoons, &allow)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Property {
+    Quality(usize),
+    Calories,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Le,
+    Ge,
+}
+
+/// `sum(coeffs[i] * spoons[i]) <op> rhs` over the ingredients.
+#[derive(Clone, Debug)]
+struct Constraint {
+    coeffs: Vec<Quality>,
+    op: Op,
+    rhs: Quality,
+}
+
+impl Constraint {
+    /// Limits the total of `property` in the cookie.
+    fn property(ingredients: &[Ingredient], property: Property, op: Op, rhs: Quality) -> Self {
+        Self {
+            coeffs: ingredients.iter().map(|i| i.get(property)).collect(),
+            op,
+            rhs,
+        }
+    }
+
+    /// Limits the spoons of a single ingredient.
+    fn spoons(n_ingredients: usize, ingredient: usize, op: Op, rhs: Spoons) -> Self {
+        let mut coeffs = vec![Quality::from(0); n_ingredients];
+        coeffs[ingredient] = Quality::from(1);
+        Self {
+            coeffs,
+            op,
+            rhs: Quality::from(rhs),
+        }
+    }
+
+    /// Whether a total somewhere in `lo..=hi` may satisfy the constraint.
+    fn allows(&self, lo: Quality, hi: Quality) -> bool {
+        match self.op {
+            Op::Eq => lo <= self.rhs && self.rhs <= hi,
+            Op::Le => lo <= self.rhs,
+            Op::Ge => hi >= self.rhs,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Recipe {
+    spoons: Vec<Spoons>,
+    score: Quality,
+}
+
+fn min_max(mut values: impl Iterator<Item = Quality>) -> (Quality, Quality) {
+    let first = values.next().expect("no values");
+    values.fold((first, first), |(lo, hi), v| (lo.min(v), hi.max(v)))
+}
+
+/// Whether the product of non-negative `factors` is above `limit`, without
+/// overflowing where it is.
+fn product_exceeds(factors: impl Iterator<Item = Quality>, limit: Quality) -> bool {
+    let zero = Quality::from(0);
+    let mut acc = Quality::from(1);
+    for factor in factors {
+        if factor == zero {
+            return false;
+        }
+        if acc > limit / factor {
+            return true;
+        }
+        acc *= factor;
+    }
+    acc > limit
+}
+
+/// Branch and bound over the spoons of each ingredient in turn.
+struct Search<'i> {
+    ingredients: &'i [Ingredient],
+    constraints: &'i [Constraint],
+    /// Per ingredient, the lowest and highest value of each quality among
+    /// it and the ingredients after it.
+    quality_ranges: Vec<[(Quality, Quality); N_QUALITIES]>,
+    /// The same for constraint coefficients.
+    coeff_ranges: Vec<Vec<(Quality, Quality)>>,
+    spoons: Vec<Spoons>,
+    best: Option<Recipe>,
+    /// Many splits of the first ingredients give the same constraint
+    /// totals, whether the rest can still satisfy them only depends on
+    /// those.
+    reachable: Memo<(usize, Spoons, Vec<Quality>), bool>,
+}
+
+impl Search<'_> {
+    fn add_totals(&self, next: usize, totals: &[Quality], amount: Spoons) -> Vec<Quality> {
+        let amount = Quality::from(amount);
+        let coeffs = self.constraints.iter().map(|c| c.coeffs[next] * amount);
+        totals.iter().zip(coeffs).map(|(&t, c)| t + c).collect()
+    }
+
+    /// Whether `left` spoons of the ingredients from `next` on can be added
+    /// to `totals` so that every constraint holds.
+    fn reachable(&mut self, next: usize, left: Spoons, totals: &[Quality]) -> bool {
+        let key = (next, left, totals.to_vec());
+        if let Some(&known) = self.reachable.get(&key) {
+            return known;
+        }
+        let ret = if next + 1 == self.ingredients.len() {
+            let totals = self.add_totals(next, totals, left);
+            self.constraints
+                .iter()
+                .zip(&totals)
+                .all(|(c, &total)| c.allows(total, total))
+        } else {
+            let left_q = Quality::from(left);
+            let in_range = self.constraints.iter().enumerate().all(|(c, constraint)| {
+                let (lo, hi) = self.coeff_ranges[next][c];
+                constraint.allows(totals[c] + lo * left_q, totals[c] + hi * left_q)
+            });
+            in_range
+                && (0..=left).any(|amount| {
+                    let totals = self.add_totals(next, totals, amount);
+                    self.reachable(next + 1, left - amount, &totals)
+                })
+        };
+        self.reachable.insert(key, ret);
+        ret
+    }
+
+    /// `cookie` and `totals` (one per constraint) cover the ingredients
+    /// before `next`, `left` spoons are still to be added.
+    fn go(&mut self, next: usize, left: Spoons, cookie: Cookie, totals: Vec<Quality>) {
+        if !self.reachable(next, left, &totals) {
+            return;
+        }
+        let left_q = Quality::from(left);
+
+        // every spoon adds at most the best remaining value of a quality
+        if let Some(best) = &self.best {
+            let zero = Quality::from(0);
+            let maxima = (0..N_QUALITIES)
+                .map(|q| (cookie.qualities[q] + self.quality_ranges[next][q].1 * left_q).max(zero));
+            if !product_exceeds(maxima, best.score) {
+                return;
+            }
+        }
+
+        let ingredient = &self.ingredients[next];
+        if next + 1 == self.ingredients.len() {
+            // the constraints hold, `reachable` checked the last ingredient
+            let score = cookie.add(left, ingredient).score();
+            if self.best.as_ref().is_none_or(|b| score > b.score) {
+                self.spoons[next] = left;
+                self.best = Some(Recipe {
+                    spoons: self.spoons.clone(),
+                    score,
+                });
+            }
+            return;
+        }
+
+        for amount in (0..=left).rev() {
+            self.spoons[next] = amount;
+            let totals = self.add_totals(next, &totals, amount);
+            self.go(
+                next + 1,
+                left - amount,
+                cookie.add(amount, ingredient),
+                totals,
+            );
+        }
+    }
 }
 
-fn choose_best_recursive(
-    cookie: Cookie,
+/// The best scoring split of `total_spoons` satisfying `constraints`, or
+/// `None` if no split satisfies them.
+fn choose_best(
     ingredients: &[Ingredient],
-    total_spoons: Quality,
-    allow: &impl Fn(&Cookie) -> bool,
-) -> Option<Quality> {
-    match ingredients {
-        [] | [_] => unreachable!(),
-        [first, second] => (0..total_spoons + 1)
-            .filter_map(|take_first| {
-                let take_second = total_spoons - take_first;
-                let cookie = cookie.add(take_first, first).add(take_second, second);
-                allow(&cookie).then(|| cookie.score())
-            })
-            .max(),
-        [first, rest @ ..] => (0..total_spoons + 1)
-            .filter_map(|take_first| {
-                let take_rest = total_spoons - take_first;
-                choose_best_recursive(cookie.add(take_first, first), rest, take_rest, allow)
-            })
-            .max(),
+    total_spoons: Spoons,
+    constraints: &[Constraint],
+) -> Option<Recipe> {
+    assert!(!ingredients.is_empty());
+    for c in constraints {
+        assert_eq!(c.coeffs.len(), ingredients.len());
     }
+
+    let quality_ranges = (0..ingredients.len())
+        .map(|first| {
+            std::array::from_fn(|q| min_max(ingredients[first..].iter().map(|i| i.qualities[q])))
+        })
+        .collect();
+    let coeff_ranges = (0..ingredients.len())
+        .map(|first| {
+            constraints
+                .iter()
+                .map(|c| min_max(c.coeffs[first..].iter().copied()))
+                .collect()
+        })
+        .collect();
+
+    let mut search = Search {
+        ingredients,
+        constraints,
+        quality_ranges,
+        coeff_ranges,
+        spoons: vec![0; ingredients.len()],
+        best: None,
+        reachable: Memo::new(),
+    };
+    let totals = vec![Quality::from(0); constraints.len()];
+    search.go(0, total_spoons, Cookie::default(), totals);
+    search.best
 }
 
-fn solve_both(input: &str) -> (Quality, Quality) {
-    let ingredients = input
+fn parse_ingredients(input: &str) -> Vec<Ingredient> {
+    input
         .lines()
         .filter(|l| !l.is_empty())
         .map(Ingredient::parse)
-        .collect::<Vec<_>>();
-    let first = choose_best(&ingredients, 100, |_| true).unwrap();
-    let second = choose_best(&ingredients, 100, |c| c.calories == 500).unwrap();
+        .collect()
+}
+
+fn solve_both(input: &str) -> (Quality, Quality) {
+    let ingredients = parse_ingredients(input);
+    let first = choose_best(&ingredients, 100, &[]).unwrap().score;
+    let calories =
+        Constraint::property(&ingredients, Property::Calories, Op::Eq, Quality::from(500));
+    let second = choose_best(&ingredients, 100, &[calories]).unwrap().score;
     (first, second)
 }
 
 #[test]
 fn example() {
-    assert_eq!(solve_both(INPUT), (62842880, 57600000));
+    assert_eq!(
+        solve_both(INPUT),
+        (Quality::from(62842880), Quality::from(57600000))
+    );
+}
+
+#[test]
+fn large_total() {
+    let ingredients = parse_ingredients(INPUT_LARGE);
+    let best = choose_best(&ingredients, 1000, &[]).unwrap();
+    let expected = (0..=1000i128)
+        .map(|a| {
+            let b = 1000 - a;
+            [5 * a - b, 5 * a + 2 * b, 3 * b - a, 4 * a + 5 * b]
+                .iter()
+                .map(|&q| q.max(0))
+                .product::<i128>()
+        })
+        .max()
+        .unwrap();
+    assert!(expected > i32::MAX as i128);
+    assert_eq!(best.score.to_string(), expected.to_string());
+}
+
+#[test]
+fn constraints() {
+    let ingredients = parse_ingredients(INPUT4);
+    let best = choose_best(&ingredients, 100, &[]).unwrap();
+    assert_eq!(best.spoons.iter().sum::<Spoons>(), 100);
+
+    let with_calories =
+        |op, rhs| Constraint::property(&ingredients, Property::Calories, op, Quality::from(rhs));
+    let sets = [
+        vec![],
+        vec![with_calories(Op::Eq, 500)],
+        vec![
+            with_calories(Op::Le, 450),
+            Constraint::spoons(4, 0, Op::Ge, 30),
+        ],
+        vec![
+            Constraint::property(
+                &ingredients,
+                Property::Quality(2),
+                Op::Ge,
+                Quality::from(150),
+            ),
+            Constraint::spoons(4, 3, Op::Le, 20),
+        ],
+        vec![with_calories(Op::Eq, 301)],
+    ];
+
+    for constraints in sets {
+        let mut expected = None;
+        for a in 0..=100 {
+            for b in 0..=100 - a {
+                for c in 0..=100 - a - b {
+                    let spoons = [a, b, c, 100 - a - b - c];
+                    let ok = constraints.iter().all(|con| {
+                        let total = spoons
+                            .iter()
+                            .zip(&con.coeffs)
+                            .map(|(&s, &k)| Quality::from(s) * k)
+                            .sum::<Quality>();
+                        con.allows(total, total)
+                    });
+                    if !ok {
+                        continue;
+                    }
+                    let cookie = spoons
+                        .iter()
+                        .zip(&ingredients)
+                        .fold(Cookie::default(), |cookie, (&s, i)| cookie.add(s, i));
+                    expected = expected.max(Some(cookie.score()));
+                }
+            }
+        }
+
+        let found = choose_best(&ingredients, 100, &constraints);
+        assert_eq!(found.as_ref().map(|r| r.score), expected);
+        if let Some(found) = found {
+            let cookie = found
+                .spoons
+                .iter()
+                .zip(&ingredients)
+                .fold(Cookie::default(), |cookie, (&s, i)| cookie.add(s, i));
+            assert_eq!(cookie.score(), found.score);
+        }
+    }
 }
 
+const INPUT_LARGE: &str = "
+Frosting: capacity 5, durability 5, flavor -1, texture 4, calories 5
+Sugar: capacity -1, durability 2, flavor 3, texture 5, calories 1
+";
+
+const INPUT4: &str = "
+Sprinkles: capacity 2, durability 0, flavor -2, texture 0, calories 3
+Butterscotch: capacity 0, durability 5, flavor -3, texture 0, calories 3
+Chocolate: capacity 0, durability 0, flavor 5, texture -1, calories 8
+Candy: capacity 0, durability -1, flavor 0, texture 5, calories 8
+";
+
 const INPUT: &str = "
 Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
 Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3
@@ -1,117 +1,158 @@
 #![cfg(test)]
 
-type Amount = u8;
+use std::collections::HashMap;
 
-const N_TRAITS: usize = 10;
+type Amount = u8;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Criterion {
     Exact,
     GreaterThen,
     LessThen,
 }
 
-fn trait_to_idx(trait_: &str) -> usize {
-    match trait_ {
-        "children" => 0,
-        "cats" => 1,
-        "samoyeds" => 2,
-        "pomeranians" => 3,
-        "akitas" => 4,
-        "vizslas" => 5,
-        "goldfish" => 6,
-        "trees" => 7,
-        "cars" => 8,
-        "perfumes" => 9,
-        _ => unreachable!(),
+impl Criterion {
+    fn matches(self, aunt: Amount, reading: Amount) -> bool {
+        match self {
+            Criterion::Exact => aunt == reading,
+            Criterion::GreaterThen => aunt > reading,
+            Criterion::LessThen => aunt < reading,
+        }
     }
 }
 
-#[rustfmt::skip]
-static IDX_TO_CRITERION: [Criterion; N_TRAITS] = [
-    Criterion::Exact,       // children
-    Criterion::GreaterThen, // cats
-    Criterion::Exact,       // samoyeds
-    Criterion::LessThen,    // pomeranians
-    Criterion::Exact,       // akitas
-    Criterion::Exact,       // vizslas
-    Criterion::LessThen,    // goldfish
-    Criterion::GreaterThen, // trees
-    Criterion::Exact,       // cars
-    Criterion::Exact,       // perfumes
-];
-
-struct Description {
-    traits: [Amount; N_TRAITS],
+/// What the MFCSAM found, one `trait: amount` per line. An amount may be
+/// prefixed with `>` or `<` when the real value is above or below it.
+struct Readout {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    amounts: Vec<Amount>,
+    criteria: Vec<Criterion>,
 }
 
-struct PartialDescription {
-    traits: Description,
-    known: [bool; N_TRAITS],
-}
-
-impl PartialDescription {
-    fn parse(s: &str) -> Self {
-        let mut traits = [0; N_TRAITS];
-        let mut known = [false; N_TRAITS];
+impl Readout {
+    fn parse(input: &str) -> Self {
+        let mut ret = Self {
+            names: Vec::new(),
+            index: HashMap::new(),
+            amounts: Vec::new(),
+            criteria: Vec::new(),
+        };
+        for line in input.lines().filter(|l| !l.is_empty()) {
+            let (name, amount) = line.split_once(": ").unwrap();
+            let (criterion, amount) = if let Some(above) = amount.strip_prefix('>') {
+                (Criterion::GreaterThen, above)
+            } else if let Some(below) = amount.strip_prefix('<') {
+                (Criterion::LessThen, below)
+            } else {
+                (Criterion::Exact, amount)
+            };
+            let old = ret.index.insert(name.to_owned(), ret.names.len());
+            assert!(old.is_none(), "trait {name} read twice");
+            ret.names.push(name.to_owned());
+            let amount = amount
+                .parse()
+                .unwrap_or_else(|_| panic!("bad amount {amount:?} for {name}"));
+            ret.amounts.push(amount);
+            ret.criteria.push(criterion);
+        }
+        ret
+    }
 
+    /// Traits the readout does not cover count against the aunt.
+    fn parse_aunt(&self, s: &str) -> Aunt {
+        let mut known = vec![None; self.names.len()];
+        let mut unknown = 0;
         let (_prefix, parts) = s.split_once(": ").unwrap();
         for part in parts.split(", ") {
             let (trait_, amount) = part.split_once(": ").unwrap();
-            let idx = trait_to_idx(trait_);
-            assert!(!known[idx]);
-            traits[idx] = amount.parse().unwrap();
-            known[idx] = true;
+            let Some(&idx) = self.index.get(trait_) else {
+                unknown += 1;
+                continue;
+            };
+            assert!(known[idx].is_none());
+            known[idx] = Some(amount.parse().unwrap());
         }
+        Aunt { known, unknown }
+    }
 
-        Self {
-            traits: Description { traits },
-            known,
-        }
+    fn agrees(&self, idx: usize, amount: Amount, use_criteria: bool) -> bool {
+        let criterion = if use_criteria {
+            self.criteria[idx]
+        } else {
+            Criterion::Exact
+        };
+        criterion.matches(amount, self.amounts[idx])
     }
 
-    fn matches_plain(&self, target: &Description) -> bool {
-        (0..N_TRAITS).all(|i| {
-            !self.known[i] || self.traits.traits[i] == target.traits[i]
-        })
+    /// Every aunt by how many of her known traits agree with the readout,
+    /// fewest disagreements and then lowest number breaking ties.
+    fn rank(&self, aunts: &[Aunt], use_criteria: bool) -> Vec<Ranked> {
+        let mut ranked = aunts
+            .iter()
+            .enumerate()
+            .map(|(i, aunt)| {
+                let (mut agree, mut disagree) = (0, aunt.unknown);
+                for (idx, amount) in aunt.known.iter().enumerate() {
+                    match amount {
+                        Some(a) if self.agrees(idx, *a, use_criteria) => agree += 1,
+                        Some(_) => disagree += 1,
+                        None => (),
+                    }
+                }
+                Ranked {
+                    number: i + 1,
+                    agree,
+                    disagree,
+                }
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by_key(|r| (std::cmp::Reverse(r.agree), r.disagree, r.number));
+        ranked
     }
-    
-    fn matches_accurately(&self, target: &Description) -> bool {
-        #[rustfmt::skip]
-        let ret = (0..N_TRAITS).all(|i| {
-            if !self.known[i] {
-                return true;
-            }
-            match IDX_TO_CRITERION[i] {
-                Criterion::Exact       => self.traits.traits[i] == target.traits[i],
-                Criterion::GreaterThen => self.traits.traits[i]  > target.traits[i],
-                Criterion::LessThen    => self.traits.traits[i]  < target.traits[i],
-            }
-        });
-        ret
+
+    /// The first aunt with no disagreeing trait.
+    fn find(&self, aunts: &[Aunt], use_criteria: bool) -> Option<usize> {
+        aunts
+            .iter()
+            .position(|aunt| {
+                aunt.unknown == 0
+                    && aunt.known.iter().enumerate().all(|(idx, amount)| {
+                        amount.is_none_or(|a| self.agrees(idx, a, use_criteria))
+                    })
+            })
+            .map(|i| i + 1)
     }
 }
 
-const AUNT_SUE: Description = Description {
-    traits: [3, 7, 2, 3, 0, 0, 5, 3, 2, 1],
-};
+struct Aunt {
+    /// Indexed like the readout's traits.
+    known: Vec<Option<Amount>>,
+    /// How many of her traits the readout does not cover.
+    unknown: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Ranked {
+    number: usize,
+    agree: usize,
+    disagree: usize,
+}
 
+/// The readout, a blank line, then one aunt per line.
 fn solve_both(input: &str) -> (usize, usize) {
-    let candidates = input
+    let (readout, aunts) = input
+        .trim_start()
+        .split_once("\n\n")
+        .expect("no blank line after the readout");
+    let readout = Readout::parse(readout);
+    let aunts = aunts
         .lines()
         .filter(|l| !l.is_empty())
-        .map(PartialDescription::parse)
+        .map(|l| readout.parse_aunt(l))
         .collect::<Vec<_>>();
-    let first = candidates
-        .iter()
-        .enumerate()
-        .find_map(|(i, d)| d.matches_plain(&AUNT_SUE).then_some(i + 1))
-        .unwrap();
-    let second = candidates
-        .iter()
-        .enumerate()
-        .find_map(|(i, d)| d.matches_accurately(&AUNT_SUE).then_some(i + 1))
-        .unwrap();
+    let first = readout.find(&aunts, false).unwrap();
+    let second = readout.find(&aunts, true).unwrap();
     (first, second)
 }
 
@@ -120,7 +161,69 @@ fn example() {
     assert_eq!(solve_both(INPUT), (2, 3));
 }
 
+#[test]
+fn ranking() {
+    let readout = Readout::parse(
+        "
+wheels: 4
+doors: >2
+seats: <8
+",
+    );
+    let aunts = [
+        "Sue 1: wheels: 4, doors: 2",
+        "Sue 2: seats: 7, doors: 5, wheels: 3",
+        "Sue 3: wheels: 4",
+        "Sue 4: doors: 4, seats: 2",
+    ]
+    .map(|l| readout.parse_aunt(l));
+
+    assert_eq!(readout.find(&aunts, true), Some(3));
+    assert_eq!(readout.find(&aunts[..2], true), None);
+    assert_eq!(readout.find(&aunts, false), Some(1));
+
+    let order = |use_criteria| {
+        readout
+            .rank(&aunts, use_criteria)
+            .iter()
+            .map(|r| (r.number, r.agree, r.disagree))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(order(true), [(4, 2, 0), (2, 2, 1), (3, 1, 0), (1, 1, 1)]);
+    assert_eq!(order(false), [(1, 2, 0), (3, 1, 0), (4, 0, 2), (2, 0, 3)]);
+
+    let aunts =
+        ["Sue 1: wheels: 4, wings: 2", "Sue 2: wheels: 4, doors: 3"].map(|l| readout.parse_aunt(l));
+    assert_eq!(readout.find(&aunts, true), Some(2));
+    assert_eq!(readout.find(&aunts[..1], true), None);
+    assert_eq!(
+        readout.rank(&aunts, true)[1],
+        Ranked {
+            number: 1,
+            agree: 1,
+            disagree: 1
+        }
+    );
+}
+
+#[test]
+#[should_panic(expected = "bad amount \"\" for doors")]
+fn empty_amount() {
+    Readout::parse("doors: >");
+}
+
 const INPUT: &str = "
+children: 3
+cats: >7
+samoyeds: 2
+pomeranians: <3
+akitas: 0
+vizslas: 0
+goldfish: <5
+trees: >3
+cars: 2
+perfumes: 1
+
 Sue 1: children: 0
 Sue 2: children: 3, cats: 7, goldfish: 5
 Sue 2: children: 3, cats: 8, goldfish: 4
@@ -1,61 +1,214 @@
 #![cfg(test)]
 
-type Volume = u8;
-type Num = usize;
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
 
-fn n_different_minimal_ways(target: Volume, containers: &[Volume]) -> (Num, Num) {
-    use std::cmp::Ordering;
+type Volume = u32;
+// a hundred 1 litre containers already hold 50 litres in more than 2^64 ways
+#[cfg(not(any(aoc_checked, aoc_wide)))]
+type Num = u64;
+#[cfg(aoc_checked)]
+type Num = numeric::Checked<u64>;
+#[cfg(aoc_wide)]
+type Num = numeric::Checked<numeric::U256>;
 
+/// `ways[k]` is the number of different sets of `k` containers holding
+/// exactly `target`.
+fn ways_by_size(target: Volume, containers: &[Volume]) -> Vec<Num> {
     assert_ne!(target, 0);
+    assert!(containers.iter().all(|&c| c != 0));
 
-    let mut n_different = 0;
-    let mut n_different_minimal = 0;
-    let mut n_minimal = Num::MAX;
-
-    let mut volumes = vec![(0 as Volume, 0)];
-    let mut next = Vec::new();
+    // no set can have more containers than the smallest ones which fit
+    let mut sorted = containers.to_vec();
+    sorted.sort_unstable();
+    let max_size = sorted
+        .iter()
+        .scan(0u64, |total, &c| {
+            *total += u64::from(c);
+            (*total <= u64::from(target)).then_some(())
+        })
+        .count();
 
+    // table[v * (max_size + 1) + k]: sets of k containers holding v
+    let width = max_size + 1;
+    let zero = Num::from(0u8);
+    let mut table = vec![zero; (target as usize + 1) * width];
+    table[0] = Num::from(1u8);
     for &c in containers {
-        for &(v, n_containers) in &volumes {
-            next.push((v, n_containers));
-            let Some(inc) = v.checked_add(c).filter(|&inc| inc <= target) else {
-                continue;
-            };
-            let n_containers = n_containers + 1;
-            if inc == target {
-                n_different += 1;
-                match n_containers.cmp(&n_minimal) {
-                    Ordering::Less => {
-                        n_minimal = n_containers;
-                        n_different_minimal = 1;
-                    }
-                    Ordering::Equal => n_different_minimal += 1,
-                    Ordering::Greater => (),
+        for v in (c..=target).rev() {
+            let (v, from) = (v as usize * width, (v - c) as usize * width);
+            for k in (1..width).rev() {
+                let add = table[from + k - 1];
+                if add != zero {
+                    table[v + k] += add;
                 }
-                continue;
             }
-            next.push((inc, n_containers));
         }
+    }
+
+    table.split_off(target as usize * width)
+}
+
+fn n_different_minimal_ways(target: Volume, containers: &[Volume]) -> (Num, Num) {
+    let ways = ways_by_size(target, containers);
+    let zero = Num::from(0u8);
+    let n_different = ways.iter().copied().sum();
+    let n_minimal = ways.iter().copied().find(|&n| n != zero).unwrap_or(zero);
+    (n_different, n_minimal)
+}
+
+const UNREACHABLE: u32 = u32::MAX;
+
+/// Sets of containers holding exactly the target, as ascending indices
+/// into the containers. Depth first with dead ends pruned up front, so
+/// every step leads to a set.
+struct Subsets<'c> {
+    containers: &'c [Volume],
+    /// `fewest[i * (target + 1) + v]`: fewest containers from the `i`th on
+    /// holding `v`, `UNREACHABLE` when none do.
+    fewest: Vec<u32>,
+    width: usize,
+    /// Only sets of this size, the smallest possible.
+    minimal: Option<usize>,
+    chosen: Vec<usize>,
+    /// Next container to decide on, volume left and containers chosen.
+    stack: Vec<(usize, Volume, usize)>,
+}
+
+impl<'c> Subsets<'c> {
+    fn new(target: Volume, containers: &'c [Volume], minimal_only: bool) -> Self {
+        assert!(containers.iter().all(|&c| c != 0));
 
-        volumes.clear();
-        std::mem::swap(&mut volumes, &mut next);
+        let n = containers.len();
+        let width = target as usize + 1;
+        let mut fewest = vec![UNREACHABLE; (n + 1) * width];
+        fewest[n * width] = 0;
+        for i in (0..n).rev() {
+            let c = containers[i] as usize;
+            let (row, below) = fewest[i * width..].split_at_mut(width);
+            for v in 0..width {
+                let take = match v.checked_sub(c).map(|v| below[v]) {
+                    Some(f) if f != UNREACHABLE => f + 1,
+                    _ => UNREACHABLE,
+                };
+                row[v] = below[v].min(take);
+            }
+        }
+
+        let best = fewest[target as usize];
+        let mut ret = Self {
+            containers,
+            fewest,
+            width,
+            minimal: (best != UNREACHABLE && minimal_only).then_some(best as usize),
+            chosen: Vec::new(),
+            stack: Vec::new(),
+        };
+        if best != UNREACHABLE {
+            ret.stack.push((0, target, 0));
+        }
+        ret
     }
 
-    (n_different, n_different_minimal)
+    fn feasible(&self, next: usize, left: Volume, n_chosen: usize) -> bool {
+        match (self.fewest[next * self.width + left as usize], self.minimal) {
+            (UNREACHABLE, _) => false,
+            (_, None) => true,
+            (fewest, Some(size)) => n_chosen + fewest as usize == size,
+        }
+    }
 }
 
-fn solve_both(input: &str, target: Volume) -> (Num, Num) {
-    let containers = input
+impl Iterator for Subsets<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        while let Some((next, left, n_chosen)) = self.stack.pop() {
+            self.chosen.truncate(n_chosen);
+            if left == 0 {
+                return Some(self.chosen.clone());
+            }
+
+            // pushed in reverse so that taking is tried first
+            if self.feasible(next + 1, left, n_chosen) {
+                self.stack.push((next + 1, left, n_chosen));
+            }
+            let c = self.containers[next];
+            if c <= left && self.feasible(next + 1, left - c, n_chosen + 1) {
+                self.chosen.push(next);
+                self.stack.push((next + 1, left - c, n_chosen + 1));
+            }
+        }
+        None
+    }
+}
+
+fn parse_containers(input: &str) -> Vec<Volume> {
+    input
         .lines()
         .filter(|l| !l.is_empty())
         .map(|n| n.parse::<Volume>().unwrap())
-        .collect::<Vec<_>>();
-    n_different_minimal_ways(target, &containers)
+        .collect()
+}
+
+fn solve_both(input: &str, target: Volume) -> (Num, Num) {
+    n_different_minimal_ways(target, &parse_containers(input))
 }
 
 #[test]
 fn example() {
-    assert_eq!(solve_both(INPUT, 25), (4, 3));
+    assert_eq!(solve_both(INPUT, 25), (Num::from(4u8), Num::from(3u8)));
+
+    let containers = parse_containers(INPUT);
+    let all = Subsets::new(25, &containers, false).collect::<Vec<_>>();
+    assert_eq!(all, [vec![0, 3], vec![0, 4], vec![1, 2], vec![1, 3, 4]]);
+    let minimal = Subsets::new(25, &containers, true).collect::<Vec<_>>();
+    assert_eq!(minimal, [vec![0, 3], vec![0, 4], vec![1, 2]]);
+    assert_eq!(Subsets::new(26, &containers, false).next(), None);
+}
+
+#[test]
+fn large() {
+    // 2000 containers of 20 to 50 litres, only pairs of 50s are minimal
+    let containers = (0..2000).map(|i| i % 31 + 20).collect::<Vec<Volume>>();
+    let ways = ways_by_size(100, &containers);
+    assert_eq!(ways[2], Num::from(2016u16));
+    let minimal = Subsets::new(100, &containers, true)
+        .take(5)
+        .collect::<Vec<_>>();
+    assert_eq!(minimal.len(), 5);
+    for set in minimal {
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.iter().map(|&i| containers[i]).sum::<Volume>(), 100);
+    }
+
+    // small enough to check every set against the counts
+    let containers = (1..=20).collect::<Vec<Volume>>();
+    let ways = ways_by_size(60, &containers);
+    let mut by_size = vec![Num::from(0u8); ways.len()];
+    for set in Subsets::new(60, &containers, false) {
+        assert_eq!(set.iter().map(|&i| containers[i]).sum::<Volume>(), 60);
+        by_size[set.len()] += Num::from(1u8);
+    }
+    assert_eq!(ways, by_size);
+    let n_minimal = Subsets::new(60, &containers, true).count();
+    assert_eq!(Num::from(n_minimal as u64), ways[4]);
+}
+
+#[cfg(aoc_wide)]
+#[test]
+fn past_64_bits() {
+    let ways = ways_by_size(50, &[1; 100]);
+    // 100 choose 50
+    assert_eq!(ways[50].to_string(), "100891344545564193334812497256");
+}
+
+#[cfg(aoc_checked)]
+#[test]
+#[should_panic(expected = "arithmetic overflow")]
+fn past_64_bits() {
+    ways_by_size(50, &[1; 100]);
 }
 
 const INPUT: &str = "
@@ -1,123 +1,335 @@
-pub type Grid<const SIZE: usize> = [[bool; SIZE]; SIZE];
-
-fn fill_corners<const SIZE: usize>(grid: &mut Grid<SIZE>) {
-    assert_ne!(SIZE, 0);
-    grid[0][0] = true;
-    grid[0][SIZE - 1] = true;
-    grid[SIZE - 1][0] = true;
-    grid[SIZE - 1][SIZE - 1] = true;
+/// Life-like rule, `B3/S23` for Conway's. Bit `n` is set when `n` live
+/// neighbours give birth to or keep alive a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: u16,
+    survival: u16,
 }
 
-fn step<const SIZE: usize>(grid: &Grid<SIZE>, out: &mut Grid<SIZE>) {
-    assert_ne!(SIZE, 0);
-
-    for (irow, row) in grid.iter().enumerate() {
-        for (icol, &alive) in row.iter().enumerate() {
-            let mut n_neighbors = 0;
-            let mut inc = |alive: bool| n_neighbors += alive as usize;
-            if irow > 0 {
-                let above = grid[irow - 1];
-                if icol > 0 {
-                    inc(above[icol - 1]);
-                }
-                inc(above[icol]);
-                if icol < SIZE - 1 {
-                    inc(above[icol + 1]);
-                }
+impl Rule {
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+    };
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let counts = |part: &str, prefix: char| {
+            let digits = part
+                .strip_prefix(prefix)
+                .ok_or_else(|| format!("{part:?} does not start with {prefix}"))?;
+            digits
+                .chars()
+                .try_fold(0u16, |mask, ch| match ch.to_digit(10) {
+                    Some(n @ 0..=8) if mask & 1 << n == 0 => Ok(mask | 1 << n),
+                    _ => Err(format!("bad neighbour count {ch:?} in {part:?}")),
+                })
+        };
+        let (birth, survival) = s
+            .split_once('/')
+            .ok_or_else(|| format!("{s:?} is not B.../S..."))?;
+        Ok(Self {
+            birth: counts(birth, 'B')?,
+            survival: counts(survival, 'S')?,
+        })
+    }
+
+    /// Next state of 64 cells at once, `count` holding the bits of their
+    /// neighbour counts, least significant first.
+    fn apply(self, alive: u64, count: &[u64; 4]) -> u64 {
+        let mut born = 0;
+        let mut survive = 0;
+        for n in 0..=8 {
+            if (self.birth | self.survival) & 1 << n == 0 {
+                continue;
             }
-            if icol > 0 {
-                inc(row[icol - 1]);
+            let is_n = count.iter().enumerate().fold(!0, |acc, (bit, &c)| {
+                acc & if n >> bit & 1 == 1 { c } else { !c }
+            });
+            if self.birth & 1 << n != 0 {
+                born |= is_n;
             }
-            if icol < SIZE - 1 {
-                inc(row[icol + 1]);
+            if self.survival & 1 << n != 0 {
+                survive |= is_n;
             }
-            if irow < SIZE - 1 {
-                let below = grid[irow + 1];
-                if icol > 0 {
-                    inc(below[icol - 1]);
-                }
-                inc(below[icol]);
-                if icol < SIZE - 1 {
-                    inc(below[icol + 1]);
+        }
+        (!alive & born) | (alive & survive)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    /// Everything outside the grid stays dead.
+    Bounded,
+    /// Opposite edges are neighbours.
+    Torus,
+    /// The grid grows whenever life reaches its edge.
+    Infinite,
+}
+
+/// Cells packed 64 to a word, bit `i` of a row's word `w` being column
+/// `64 * w + i`. Bits past the last column are always zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    rows: usize,
+    cols: usize,
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl Grid {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let stride = cols.div_ceil(64);
+        Self {
+            rows,
+            cols,
+            stride,
+            words: vec![0; rows * stride],
+        }
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let lines = input.lines().filter(|l| !l.is_empty()).collect::<Vec<_>>();
+        let cols = lines.first().map_or(0, |l| l.len());
+        let mut grid = Self::new(lines.len(), cols);
+        for (row, line) in lines.iter().enumerate() {
+            assert_eq!(line.len(), cols);
+            for (col, ch) in line.bytes().enumerate() {
+                match ch {
+                    b'#' => grid.set(row, col, true),
+                    b'.' => (),
+                    _ => panic!("wrong character {}", char::from(ch)),
                 }
             }
-            out[irow][icol] = match (alive, n_neighbors) {
-                (false, 3) => true,
-                (true, 2 | 3) => true,
-                _ => false,
+        }
+        grid
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.row(row)[col / 64] >> (col % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, alive: bool) {
+        assert!(row < self.rows && col < self.cols);
+        let word = &mut self.words[row * self.stride + col / 64];
+        let bit = 1 << (col % 64);
+        if alive {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.stride..][..self.stride]
+    }
+
+    fn tail_mask(&self) -> u64 {
+        match self.cols % 64 {
+            0 => !0,
+            n => (1 << n) - 1,
+        }
+    }
+
+    /// Whether a live cell is in the first or last row or column.
+    fn touches_edge(&self) -> bool {
+        if self.rows == 0 {
+            return false;
+        }
+        let last_col = self.cols - 1;
+        self.row(0).iter().any(|&w| w != 0)
+            || self.row(self.rows - 1).iter().any(|&w| w != 0)
+            || (0..self.rows).any(|r| self.get(r, 0) || self.get(r, last_col))
+    }
+
+    fn step(&self, rule: Rule, wrap: bool, out: &mut Grid) {
+        assert_eq!((self.rows, self.cols), (out.rows, out.cols));
+        let dead = vec![0; self.stride];
+        let tail_mask = self.tail_mask();
+        for r in 0..self.rows {
+            let above = match r {
+                0 if wrap => self.row(self.rows - 1),
+                0 => &dead,
+                _ => self.row(r - 1),
+            };
+            let below = match r + 1 {
+                next if next < self.rows => self.row(next),
+                _ if wrap => self.row(0),
+                _ => &dead,
             };
+            let row = self.row(r);
+
+            for w in 0..self.stride {
+                let mut count = [0; 4];
+                for line in [above, row, below] {
+                    let (west, east) = neighbours(line, w, self.cols, wrap);
+                    add(&mut count, west);
+                    add(&mut count, east);
+                }
+                add(&mut count, above[w]);
+                add(&mut count, below[w]);
+
+                let next = rule.apply(row[w], &count);
+                out.words[r * self.stride + w] = if w + 1 == self.stride {
+                    next & tail_mask
+                } else {
+                    next
+                };
+            }
         }
     }
 }
 
-fn fill_grid<const SIZE: usize>(out: &mut Grid<SIZE>, input: &str) {
-    let n_rows = input
-        .lines()
-        .zip(&mut out[..])
-        .map(|(line, row)| {
-            assert_eq!(line.len(), SIZE);
-            line.as_bytes().iter().zip(row).for_each(|(&ch, cell)| {
-                *cell = match ch {
-                    b'#' => true,
-                    b'.' => false,
-                    _ => panic!("wrong character {}", char::from(ch)),
-                };
-            })
-        })
-        .count();
-    assert_eq!(n_rows, SIZE);
+/// Word `w` of `row` shifted so that every cell sees its west and east
+/// neighbours' states.
+fn neighbours(row: &[u64], w: usize, cols: usize, wrap: bool) -> (u64, u64) {
+    let mut west = (row[w] << 1) | if w > 0 { row[w - 1] >> 63 } else { 0 };
+    let mut east = (row[w] >> 1) | row.get(w + 1).map_or(0, |next| next << 63);
+    if wrap && w == 0 {
+        west |= row[(cols - 1) / 64] >> ((cols - 1) % 64) & 1;
+    }
+    if wrap && w + 1 == row.len() {
+        east |= (row[0] & 1) << ((cols - 1) % 64);
+    }
+    (west, east)
 }
 
-pub fn solve<'a, const SIZE: usize, const KEEP_CORNERS: bool>(
-    mut current: &'a mut Grid<SIZE>,
-    mut next: &'a mut Grid<SIZE>,
-    n_steps: usize,
-) -> usize {
-    if KEEP_CORNERS {
-        fill_corners(current);
+/// Adds one neighbour to 64 bit sliced counters.
+fn add(count: &mut [u64; 4], mut carry: u64) {
+    for bit in count {
+        let next = *bit & carry;
+        *bit ^= carry;
+        carry = next;
     }
-    for _ in 0..n_steps {
-        step(current, next);
-        if KEEP_CORNERS {
-            fill_corners(next);
+}
+
+/// Margin added on every side when an infinite grid has to grow.
+const GROW_BY: usize = 32;
+
+pub struct Life {
+    rule: Rule,
+    topology: Topology,
+    grid: Grid,
+    next: Grid,
+    /// Coordinates of the grid's top left cell. Infinite grids also grow up
+    /// and left, everything else uses coordinates of the initial grid.
+    origin: (i64, i64),
+    /// Cells forced to a state after every step.
+    pins: Vec<(i64, i64, bool)>,
+    pin_mask: Grid,
+    pin_alive: Grid,
+}
+
+impl Life {
+    pub fn new(grid: Grid, rule: Rule, topology: Topology) -> Self {
+        assert!(
+            topology != Topology::Infinite || rule.birth & 1 == 0,
+            "B0 rules fill an infinite grid in one step"
+        );
+        let (rows, cols) = (grid.rows, grid.cols);
+        Self {
+            rule,
+            topology,
+            next: Grid::new(rows, cols),
+            grid,
+            origin: (0, 0),
+            pins: Vec::new(),
+            pin_mask: Grid::new(rows, cols),
+            pin_alive: Grid::new(rows, cols),
         }
-        std::mem::swap(&mut current, &mut next);
     }
-    current
-        .iter()
-        .flat_map(|row| row.iter())
-        .map(|&alive| alive as usize)
-        .sum()
-}
 
-pub fn solve_both<const SIZE: usize>(input: &str, n_steps: usize) -> (usize, usize) {
-    let mut initial = [[false; SIZE]; SIZE];
-    let current = &mut [[false; SIZE]; SIZE];
-    let next = &mut [[false; SIZE]; SIZE];
-    fill_grid(&mut initial, input.trim_matches('\n'));
+    fn index(&self, row: i64, col: i64) -> Option<(usize, usize)> {
+        let row = usize::try_from(row - self.origin.0).ok()?;
+        let col = usize::try_from(col - self.origin.1).ok()?;
+        (row < self.grid.rows && col < self.grid.cols).then_some((row, col))
+    }
 
-    current.clone_from(&initial);
-    let first = solve::<SIZE, false>(current, next, n_steps);
+    pub fn get(&self, row: i64, col: i64) -> bool {
+        self.index(row, col)
+            .is_some_and(|(row, col)| self.grid.get(row, col))
+    }
 
-    current.clone_from(&initial);
-    let second = solve::<SIZE, true>(current, next, n_steps);
+    pub fn population(&self) -> usize {
+        self.grid.population()
+    }
 
-    (first, second)
-}
+    /// Keeps a cell `alive` or dead from now on, whatever the rule says.
+    pub fn pin(&mut self, row: i64, col: i64, alive: bool) {
+        if self.topology == Topology::Infinite {
+            while self.index(row, col).is_none() {
+                self.grow();
+            }
+        }
+        let (r, c) = self
+            .index(row, col)
+            .unwrap_or_else(|| panic!("pinned cell {row},{col} is off the grid"));
+        self.pins.push((row, col, alive));
+        self.pin_mask.set(r, c, true);
+        self.pin_alive.set(r, c, alive);
+        self.grid.set(r, c, alive);
+    }
 
-#[cfg(test)]
-const fn grid_size(grid: &str) -> usize {
-    let grid = grid.as_bytes();
-    let mut i = 0;
-    let mut len = 0;
-    while grid[i] == b'\n' {
-        i += 1;
+    fn grow(&mut self) {
+        let (rows, cols) = (self.grid.rows, self.grid.cols);
+        let mut grid = Grid::new(rows + 2 * GROW_BY, cols + 2 * GROW_BY);
+        for row in 0..rows {
+            for col in (0..cols).filter(|&col| self.grid.get(row, col)) {
+                grid.set(row + GROW_BY, col + GROW_BY, true);
+            }
+        }
+        self.origin.0 -= GROW_BY as i64;
+        self.origin.1 -= GROW_BY as i64;
+
+        self.pin_mask = Grid::new(grid.rows, grid.cols);
+        self.pin_alive = Grid::new(grid.rows, grid.cols);
+        self.next = Grid::new(grid.rows, grid.cols);
+        self.grid = grid;
+        for (row, col, alive) in std::mem::take(&mut self.pins) {
+            self.pin(row, col, alive);
+        }
     }
-    while grid[i] != b'\n' {
-        i += 1;
-        len += 1;
+
+    pub fn step(&mut self) {
+        if self.topology == Topology::Infinite && self.grid.touches_edge() {
+            self.grow();
+        }
+        let wrap = self.topology == Topology::Torus;
+        self.grid.step(self.rule, wrap, &mut self.next);
+        std::mem::swap(&mut self.grid, &mut self.next);
+
+        if !self.pins.is_empty() {
+            let pins = self.pin_mask.words.iter().zip(&self.pin_alive.words);
+            for (cell, (&mask, &alive)) in self.grid.words.iter_mut().zip(pins) {
+                *cell = (*cell & !mask) | alive;
+            }
+        }
     }
-    len
+
+    pub fn run(&mut self, n_steps: usize) {
+        (0..n_steps).for_each(|_| self.step());
+    }
+}
+
+pub fn solve_both(input: &str, n_steps: usize) -> (usize, usize) {
+    let initial = Grid::parse(input);
+    let (last_row, last_col) = (initial.rows as i64 - 1, initial.cols as i64 - 1);
+
+    let mut life = Life::new(initial.clone(), Rule::CONWAY, Topology::Bounded);
+    life.run(n_steps);
+    let first = life.population();
+
+    let mut life = Life::new(initial, Rule::CONWAY, Topology::Bounded);
+    for (row, col) in [(0, 0), (0, last_col), (last_row, 0), (last_row, last_col)] {
+        life.pin(row, col, true);
+    }
+    life.run(n_steps);
+    let second = life.population();
+
+    (first, second)
 }
 
 #[test]
@@ -130,8 +342,140 @@ fn example() {
 #.#..#
 ####..
 ";
+    assert_eq!(solve_both(EXAMPLE_INPUT, 5), (4, 17));
+}
+
+#[test]
+fn rules_and_topologies() {
+    assert_eq!(Rule::parse("B3/S23"), Ok(Rule::CONWAY));
+    assert_eq!(Rule::parse("B36/S23").unwrap().birth, 1 << 3 | 1 << 6);
+    assert_eq!(Rule::parse("B/S").unwrap().survival, 0);
+    assert!(Rule::parse("B9/S23").is_err());
+    assert!(Rule::parse("B33/S23").is_err());
+    assert!(Rule::parse("S23/B3").is_err());
+    assert!(Rule::parse("B3S23").is_err());
+
+    const GLIDER: &str = "
+.#.
+..#
+###
+";
+    let cells = |life: &Life, rows: std::ops::Range<i64>, cols: std::ops::Range<i64>| {
+        let mut ret = Vec::new();
+        for row in rows {
+            for col in cols.clone() {
+                if life.get(row, col) {
+                    ret.push((row, col));
+                }
+            }
+        }
+        ret
+    };
+    let glider: [(i64, i64); 5] = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+    // bounded, the glider turns into a block in the corner
+    let mut life = Life::new(Grid::parse(GLIDER), Rule::CONWAY, Topology::Bounded);
+    life.run(4);
+    assert_eq!(cells(&life, 0..3, 0..3), [(1, 1), (1, 2), (2, 1), (2, 2)]);
+
+    // a glider moves a cell diagonally every four steps, 70 of them take
+    // it round a 10x70 torus
+    let mut grid = Grid::new(10, 70);
+    glider
+        .iter()
+        .for_each(|&(r, c)| grid.set(r as usize, c as usize, true));
+    let mut life = Life::new(grid.clone(), Rule::CONWAY, Topology::Torus);
+    life.run(4 * 70);
+    assert_eq!(life.grid, grid);
+
+    // on an infinite grid it just keeps going
+    let mut life = Life::new(Grid::parse(GLIDER), Rule::CONWAY, Topology::Infinite);
+    life.pin(-5, -5, true);
+    life.run(400);
+    assert_eq!(life.population(), 6);
     assert_eq!(
-        solve_both::<{ grid_size(EXAMPLE_INPUT) }>(EXAMPLE_INPUT, 5),
-        (4, 17)
+        cells(&life, 100..103, 100..103),
+        glider.map(|(r, c)| (r + 100, c + 100))
     );
+    assert!(life.get(-5, -5));
+}
+
+#[test]
+fn matches_naive() {
+    // xorshift
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let rules = ["B3/S23", "B36/S23", "B0/S8", "B1357/S1357"].map(|r| Rule::parse(r).unwrap());
+    for (rows, cols) in [(2, 3), (5, 64), (7, 65), (12, 130)] {
+        let mut grid = Grid::new(rows, cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                grid.set(row, col, next() % 3 == 0);
+            }
+        }
+        for rule in rules {
+            for wrap in [false, true] {
+                let topology = if wrap {
+                    Topology::Torus
+                } else {
+                    Topology::Bounded
+                };
+                let mut life = Life::new(grid.clone(), rule, topology);
+                let pin = (rows as i64 / 2, cols as i64 - 1);
+                life.pin(pin.0, pin.1, true);
+
+                let mut cells = (0..rows)
+                    .map(|r| (0..cols).map(|c| grid.get(r, c)).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                cells[pin.0 as usize][pin.1 as usize] = true;
+
+                for _ in 0..10 {
+                    life.step();
+                    let mut next = cells.clone();
+                    for (r, row) in next.iter_mut().enumerate() {
+                        for (c, cell) in row.iter_mut().enumerate() {
+                            let mut n = 0;
+                            for dr in -1..=1 {
+                                for dc in -1..=1 {
+                                    let (nr, nc) = (r as i64 + dr, c as i64 + dc);
+                                    let (nr, nc) = if wrap {
+                                        (nr.rem_euclid(rows as i64), nc.rem_euclid(cols as i64))
+                                    } else if (0..rows as i64).contains(&nr)
+                                        && (0..cols as i64).contains(&nc)
+                                    {
+                                        (nr, nc)
+                                    } else {
+                                        continue;
+                                    };
+                                    if (dr, dc) != (0, 0) {
+                                        n += cells[nr as usize][nc as usize] as u16;
+                                    }
+                                }
+                            }
+                            let mask = if cells[r][c] {
+                                rule.survival
+                            } else {
+                                rule.birth
+                            };
+                            *cell = mask & 1 << n != 0;
+                        }
+                    }
+                    next[pin.0 as usize][pin.1 as usize] = true;
+                    cells = next;
+
+                    for (r, row) in cells.iter().enumerate() {
+                        for (c, &cell) in row.iter().enumerate() {
+                            assert_eq!(life.get(r as i64, c as i64), cell, "{rule:?} {r},{c}");
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -70,10 +70,6 @@ mod trie {
         pub fn len(&self) -> usize {
             self.props.len
         }
-
-        pub fn insert_seq(&mut self, seq: &[Token]) -> bool {
-            self.cursor().insert_seq(seq).complete()
-        }
     }
 
     pub struct Cursor<'a> {
@@ -84,8 +80,8 @@ mod trie {
     impl<'a> Cursor<'a> {
         pub fn reborrow<'r>(&'r mut self) -> Cursor<'r> {
             Cursor {
-                props: &mut self.props,
-                node: &mut self.node,
+                props: self.props,
+                node: self.node,
             }
         }
 
@@ -120,7 +116,7 @@ fn split_off_atom<'a>(s: &mut &'a str) -> Option<&'a str> {
     if s.is_empty() {
         return None;
     }
-    let len = if s.as_bytes().get(1).map_or(false, u8::is_ascii_lowercase) {
+    let len = if s.as_bytes().get(1).is_some_and(u8::is_ascii_lowercase) {
         2
     } else {
         1
@@ -163,7 +159,7 @@ pub fn solve_first(
     while let Some((&first, rest)) = molecule.split_first() {
         for substitution in &rules_map[first] {
             seqs.reborrow()
-                .insert_seq(&substitution)
+                .insert_seq(substitution)
                 .insert_seq(rest)
                 .complete();
         }
@@ -177,59 +173,277 @@ pub fn solve_first(
     trie.len()
 }
 
-fn reverse_rules(rules_map: &[Vec<Vec<Token>>]) -> Vec<(Vec<Token>, Token)> {
-    rules_map
-        .iter()
-        .cloned()
-        .enumerate()
-        .flat_map(|(atom, substs)| substs.into_iter().map(move |subst| (subst, atom)))
-        .collect()
+/// Cost of a symbol which can't make a part of the molecule.
+const UNPARSED: u32 = u32::MAX;
+
+/// `head => left right`, the links of a rule split into binary ones.
+struct Binary {
+    head: Token,
+    left: Token,
+    right: Token,
+    /// 1 for the first link, which stands for the whole rule, 0 for the
+    /// rest.
+    steps: u32,
 }
 
-pub fn solve_second(
-    initial: Token,
-    rules_map: &[Vec<Vec<Token>>],
-    target: Vec<Token>,
+/// The replacements as a context free grammar for CYK parsing. Atoms are
+/// symbols `0..n_atoms`, the symbols above stand for the tails of rules.
+struct Grammar {
     n_atoms: usize,
-) -> usize {
-    let reverse_rules_map = reverse_rules(rules_map);
-    let mut trie = trie::Trie::new(n_atoms);
-    trie.insert_seq(&target);
-
-    let mut seqs = vec![(target, 0)];
-
-    while let Some((unreduced, n_steps)) = seqs.pop() {
-        let n_steps = n_steps + 1;
-        for i in 0..unreduced.len() {
-            for &(ref subst, atom) in reverse_rules_map.iter() {
-                let Some(tail) = unreduced[i..].strip_prefix(&subst[..]) else {
-                    continue;
-                };
+    n_symbols: usize,
+    /// `head => child`, a step each.
+    units: Vec<(Token, Token)>,
+    binaries: Vec<Binary>,
+    /// Indices into `binaries` by left symbol.
+    by_left: Vec<Vec<usize>>,
+}
+
+/// Fewest steps making each part of the molecule from each symbol.
+struct Chart {
+    n: usize,
+    n_symbols: usize,
+    costs: Vec<u32>,
+    /// Symbols with a cost for each part.
+    parsed: Vec<Vec<Token>>,
+}
+
+impl Chart {
+    fn span(&self, i: usize, j: usize) -> usize {
+        i * (self.n + 1) + j
+    }
+
+    fn cost(&self, i: usize, j: usize, symbol: Token) -> u32 {
+        self.costs[self.span(i, j) * self.n_symbols + symbol]
+    }
 
-                if atom == initial {
-                    if unreduced.len() == subst.len() {
-                        return n_steps;
-                    } else {
-                        continue;
+    fn relax(&mut self, i: usize, j: usize, symbol: Token, cost: u32) -> bool {
+        let span = self.span(i, j);
+        let old = &mut self.costs[span * self.n_symbols + symbol];
+        if cost >= *old {
+            return false;
+        }
+        if *old == UNPARSED {
+            self.parsed[span].push(symbol);
+        }
+        *old = cost;
+        true
+    }
+}
+
+impl Grammar {
+    fn new(rules_map: &[Vec<Vec<Token>>], n_atoms: usize) -> Self {
+        let mut units = Vec::new();
+        let mut binaries = Vec::new();
+        let mut n_symbols = n_atoms;
+        for (head, substs) in rules_map.iter().enumerate() {
+            for subst in substs {
+                let len = subst.len();
+                match len {
+                    0 => panic!("rule replacing {head} with nothing"),
+                    1 => units.push((head, subst[0])),
+                    _ => {
+                        let mut head = head;
+                        for m in 0..len - 1 {
+                            let right = if m + 2 == len {
+                                subst[len - 1]
+                            } else {
+                                n_symbols += 1;
+                                n_symbols - 1
+                            };
+                            binaries.push(Binary {
+                                head,
+                                left: subst[m],
+                                right,
+                                steps: (m == 0) as u32,
+                            });
+                            head = right;
+                        }
                     }
                 }
+            }
+        }
+
+        let mut by_left = vec![Vec::new(); n_symbols];
+        for (idx, binary) in binaries.iter().enumerate() {
+            by_left[binary.left].push(idx);
+        }
+        Self {
+            n_atoms,
+            n_symbols,
+            units,
+            binaries,
+            by_left,
+        }
+    }
 
-                let new = unreduced[..i]
-                    .iter()
-                    .chain(Some(&atom))
-                    .chain(tail)
-                    .copied()
-                    .collect::<Vec<_>>();
-                if !trie.insert_seq(&new) {
-                    continue;
+    fn parse(&self, molecule: &[Token]) -> Chart {
+        let n = molecule.len();
+        let mut chart = Chart {
+            n,
+            n_symbols: self.n_symbols,
+            costs: vec![UNPARSED; (n + 1) * (n + 1) * self.n_symbols],
+            parsed: vec![Vec::new(); (n + 1) * (n + 1)],
+        };
+
+        for (i, &atom) in molecule.iter().enumerate() {
+            chart.relax(i, i + 1, atom, 0);
+        }
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                for k in i + 1..j {
+                    let left_span = chart.span(i, k);
+                    // relaxing `i..j` never touches the shorter `i..k`
+                    let lefts = std::mem::take(&mut chart.parsed[left_span]);
+                    for &left in &lefts {
+                        let left_cost = chart.cost(i, k, left);
+                        for &b in &self.by_left[left] {
+                            let b = &self.binaries[b];
+                            let right_cost = chart.cost(k, j, b.right);
+                            if right_cost != UNPARSED {
+                                chart.relax(i, j, b.head, left_cost + right_cost + b.steps);
+                            }
+                        }
+                    }
+                    chart.parsed[left_span] = lefts;
                 }
+                // unit rules may chain, and loop
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for &(head, child) in &self.units {
+                        let cost = chart.cost(i, j, child);
+                        if cost != UNPARSED {
+                            changed |= chart.relax(i, j, head, cost + 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        chart
+    }
+
+    /// A derivation of `molecule[i..j]` from `symbol` with as few steps as
+    /// the chart says.
+    fn derivation(
+        &self,
+        chart: &Chart,
+        molecule: &[Token],
+        i: usize,
+        j: usize,
+        symbol: Token,
+    ) -> Derivation {
+        let cost = chart.cost(i, j, symbol);
+        assert_ne!(cost, UNPARSED);
+        if cost == 0 {
+            assert_eq!((j - i, molecule[i]), (1, symbol));
+            return Derivation {
+                symbol,
+                children: Vec::new(),
+            };
+        }
 
-                seqs.push((new, n_steps));
+        for &(head, child) in &self.units {
+            if head == symbol && chart.cost(i, j, child) == cost - 1 {
+                return Derivation {
+                    symbol,
+                    children: vec![self.derivation(chart, molecule, i, j, child)],
+                };
+            }
+        }
+        for b in &self.binaries {
+            if b.head != symbol || b.steps == 0 {
+                continue;
+            }
+            if let Some(children) = self.chain(chart, molecule, b, i, j, cost - 1) {
+                return Derivation { symbol, children };
             }
         }
+        unreachable!()
+    }
+
+    /// Derivations of the symbols of a rule's links, which together make
+    /// `molecule[i..j]` in `cost` steps.
+    fn chain(
+        &self,
+        chart: &Chart,
+        molecule: &[Token],
+        link: &Binary,
+        i: usize,
+        j: usize,
+        cost: u32,
+    ) -> Option<Vec<Derivation>> {
+        let k = (i + 1..j).find(|&k| {
+            let left = chart.cost(i, k, link.left);
+            let right = chart.cost(k, j, link.right);
+            left != UNPARSED && right != UNPARSED && left + right == cost
+        })?;
+
+        let mut children = vec![self.derivation(chart, molecule, i, k, link.left)];
+        if link.right < self.n_atoms {
+            children.push(self.derivation(chart, molecule, k, j, link.right));
+        } else {
+            let next = self.binaries.iter().find(|b| b.head == link.right).unwrap();
+            let rest = chart.cost(k, j, link.right);
+            children.extend(self.chain(chart, molecule, next, k, j, rest).unwrap());
+        }
+        Some(children)
+    }
+}
+
+/// How a symbol turns into a part of the molecule. Leaves are the
+/// molecule's atoms, every other node a replacement.
+pub struct Derivation {
+    symbol: Token,
+    children: Vec<Derivation>,
+}
+
+impl Derivation {
+    pub fn n_steps(&self) -> usize {
+        match self.children.len() {
+            0 => 0,
+            _ => 1 + self.children.iter().map(Self::n_steps).sum::<usize>(),
+        }
     }
 
-    unreachable!()
+    /// The molecule after every step, starting from the root symbol and
+    /// always replacing the leftmost atom still to be replaced.
+    pub fn rewrites(&self, i: &Interner) -> Vec<String> {
+        let show = |nodes: &[&Derivation]| nodes.iter().map(|d| i.strs[d.symbol]).collect();
+        let mut nodes = vec![self];
+        let mut ret = vec![show(&nodes)];
+        while let Some(pos) = nodes.iter().position(|d| !d.children.is_empty()) {
+            let node = nodes[pos];
+            nodes.splice(pos..=pos, &node.children);
+            ret.push(show(&nodes));
+        }
+        ret
+    }
+}
+
+/// Fewest replacements making `target` from `initial`, or `None` if the
+/// replacements can't make it at all.
+pub fn shortest_derivation(
+    initial: Token,
+    rules_map: &[Vec<Vec<Token>>],
+    target: &[Token],
+    n_atoms: usize,
+) -> Option<Derivation> {
+    let grammar = Grammar::new(rules_map, n_atoms);
+    let chart = grammar.parse(target);
+    let n = target.len();
+    (n > 0 && chart.cost(0, n, initial) != UNPARSED)
+        .then(|| grammar.derivation(&chart, target, 0, n, initial))
+}
+
+pub fn solve_second(
+    initial: Token,
+    rules_map: &[Vec<Vec<Token>>],
+    target: &[Token],
+    n_atoms: usize,
+) -> Option<usize> {
+    shortest_derivation(initial, rules_map, target, n_atoms).map(|d| d.n_steps())
 }
 
 pub fn parse_input<'a>(input: &'a str, i: &mut Interner<'a>) -> (Vec<Token>, Vec<Vec<Vec<Token>>>) {
@@ -238,6 +452,9 @@ pub fn parse_input<'a>(input: &'a str, i: &mut Interner<'a>) -> (Vec<Token>, Vec
     for Rule { head, subts } in rules.lines().map(|l| Rule::parse_interning(l, i)) {
         rules_map.entry(head).or_default().push(subts);
     }
+    let molecule = atom_tokenize(molecule)
+        .map(|atom| i.insert(atom))
+        .collect::<Vec<_>>();
     let n_atoms = i.len();
     let rules = {
         let mut map = vec![Vec::new(); n_atoms];
@@ -246,22 +463,21 @@ pub fn parse_input<'a>(input: &'a str, i: &mut Interner<'a>) -> (Vec<Token>, Vec
         }
         map
     };
-    let molecule = atom_tokenize(molecule)
-        .map(|atom| i.insert(atom))
-        .collect::<Vec<_>>();
 
     (molecule, rules)
 }
 
 pub const INITIAL: &str = "e";
 
-pub fn solve_both(input: &str) -> (usize, usize) {
+/// The second part is `None` when the molecule can't be made from `e`.
+pub fn solve_both(input: &str) -> (usize, Option<usize>) {
     let mut i = Interner::default();
     let (molecule, rules) = parse_input(input, &mut i);
+    let initial = i.insert(INITIAL);
     let n_atoms = i.len();
 
     let first = solve_first(&molecule, &rules, n_atoms);
-    let second = solve_second(i.insert(INITIAL), &rules, molecule.clone(), n_atoms);
+    let second = solve_second(initial, &rules, &molecule, n_atoms);
 
     (first, second)
 }
@@ -298,5 +514,36 @@ HOHOHO
     let initial = i.insert(INITIAL);
     let n_atoms = i.len();
 
-    assert_eq!(solve_second(initial, &rules, molecule, n_atoms), 6);
+    assert_eq!(solve_second(initial, &rules, &molecule, n_atoms), Some(6));
+
+    let derivation = shortest_derivation(initial, &rules, &molecule, n_atoms).unwrap();
+    let rewrites = derivation.rewrites(&i);
+    assert_eq!(rewrites.len(), 7);
+    assert_eq!(rewrites[0], "e");
+    assert_eq!(rewrites[6], "HOHOHO");
+    for pair in rewrites.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        let applies = rules.iter().enumerate().any(|(head, substs)| {
+            let head = i.strs[head];
+            substs.iter().any(|subst| {
+                let subst = subst.iter().map(|&t| i.strs[t]).collect::<String>();
+                from.match_indices(head).any(|(pos, _)| {
+                    format!("{}{subst}{}", &from[..pos], &from[pos + head.len()..]) == *to
+                })
+            })
+        });
+        assert!(applies, "{from} => {to}");
+    }
+}
+
+#[test]
+fn underivable() {
+    let steps = |input| solve_both(input).1;
+    // H always stays left of O
+    assert_eq!(steps("e => HO\nH => HH\nO => OO\n\nHHOOO"), Some(4));
+    assert_eq!(steps("e => HO\nH => HH\nO => OO\n\nOH"), None);
+    assert_eq!(steps("e => HO\nH => HH\nO => OO\n\nHCa"), None);
+    // unit rules going round in circles
+    assert_eq!(steps("e => H\nH => O\nO => H\n\nO"), Some(2));
+    assert_eq!(steps("e => H\nH => O\nO => H\n\nOO"), None);
 }
@@ -1,45 +1,94 @@
-pub use primal::Sieve;
-
-pub type Num = usize;
-
-fn sum_of_divisors(sieve: &Sieve, n: Num) -> Num {
-    sieve
-        .factor(n)
-        .unwrap()
-        .into_iter()
-        .map(|(p, k)| (0..=k).map(|k| p.pow(k as _)).sum::<Num>())
-        .product::<Num>()
-}
-
-fn n_presents(sieve: &Sieve, n_house: Num) -> Num {
-    sum_of_divisors(sieve, n_house) * 10
-}
-
-pub fn solve_first(sieve: &Sieve, n_target: Num) -> Num {
-    (1..)
-        .find(|&n_house| n_presents(sieve, n_house) >= n_target)
-        .unwrap()
-}
-
-fn n_presents_modified(n_house: Num) -> Num {
-    (1..=50)
-        .filter(|elf| n_house % elf == 0)
-        .map(|elf| n_house / elf)
-        .sum::<Num>() * 11
-}
-
-pub fn solve_second(n_target: Num) -> Num {
-    (1..).find(|&n_house| n_presents_modified(n_house) >= n_target).unwrap()
-}
-
-pub fn solve_both(input: Num) -> (Num, Num) {
-    let sieve = Sieve::new(input / 10);
-    let first = solve_first(&sieve, input);
-    let second = solve_second(input);
-    (first, second)
-}
-
-#[test]
-fn example() {
-    assert_eq!(solve_both(34000000), (786240, 831600));
-}
+pub type Num = usize;
+
+/// How the elves deliver: elf `n` leaves `multiplier * n` presents at
+/// houses `n`, `2n`, ... stopping after `max_houses` of them.
+#[derive(Clone, Copy, Debug)]
+pub struct Elves {
+    pub multiplier: Num,
+    pub max_houses: Option<Num>,
+}
+
+impl Elves {
+    pub const FIRST: Elves = Elves {
+        multiplier: 10,
+        max_houses: None,
+    };
+    pub const SECOND: Elves = Elves {
+        multiplier: 11,
+        max_houses: Some(50),
+    };
+
+    /// Presents at every house up to `bound`, index 0 being unused.
+    pub fn presents(&self, bound: Num) -> Vec<Num> {
+        let mut presents = vec![0; bound + 1];
+        let max_houses = self.max_houses.unwrap_or(Num::MAX);
+        for elf in 1..=bound {
+            let gift = elf * self.multiplier;
+            for house in (elf..=bound).step_by(elf).take(max_houses) {
+                presents[house] += gift;
+            }
+        }
+        presents
+    }
+
+    /// First house with at least `target` presents.
+    pub fn first_house(&self, target: Num) -> Num {
+        assert_ne!(self.multiplier, 0);
+        assert_ne!(self.max_houses, Some(0));
+        // elf n alone brings house n enough
+        let bound = target.div_ceil(self.multiplier).max(1);
+        self.presents(bound)
+            .iter()
+            .skip(1)
+            .position(|&p| p >= target)
+            .unwrap()
+            + 1
+    }
+}
+
+pub fn solve_both(input: Num) -> (Num, Num) {
+    let first = Elves::FIRST.first_house(input);
+    let second = Elves::SECOND.first_house(input);
+    (first, second)
+}
+
+#[test]
+fn example() {
+    assert_eq!(
+        Elves::FIRST.presents(9)[1..],
+        [10, 30, 40, 70, 60, 120, 80, 150, 130]
+    );
+    assert_eq!(solve_both(34000000), (786240, 831600));
+}
+
+#[test]
+fn limits() {
+    let brute_force = |elves: Elves, house: Num| {
+        (1..=house)
+            .filter(|&elf| house.is_multiple_of(elf))
+            .filter(|elf| elves.max_houses.is_none_or(|max| house / elf <= max))
+            .map(|elf| elf * elves.multiplier)
+            .sum::<Num>()
+    };
+    for elves in [
+        Elves::FIRST,
+        Elves::SECOND,
+        Elves {
+            multiplier: 1,
+            max_houses: Some(1),
+        },
+        Elves {
+            multiplier: 3,
+            max_houses: Some(4),
+        },
+    ] {
+        let presents = elves.presents(300);
+        for (house, &n) in presents.iter().enumerate().skip(1) {
+            assert_eq!(n, brute_force(elves, house), "{elves:?}");
+        }
+        for target in [0, 1, 50, 777, 2000] {
+            let expected = (1..).find(|&h| brute_force(elves, h) >= target).unwrap();
+            assert_eq!(elves.first_house(target), expected, "{elves:?} {target}");
+        }
+    }
+}
@@ -1,3 +1,8 @@
+#[path = "../../common/trace.rs"]
+mod trace;
+
+use trace::{Printer, Tracer};
+
 type Num = i32;
 
 enum Instruction {
@@ -38,18 +43,32 @@ impl<'a> Cpu<'a> {
         }
     }
 
-    fn tick(&mut self) -> bool {
+    fn tick(&mut self, tracer: &mut impl Tracer) -> bool {
         match self.state {
             State::Addx(n) => {
                 self.x += n;
                 self.pc += 1;
                 self.state = State::Decode;
+                tracer.emit(
+                    "addx",
+                    &[
+                        ("pc", self.pc.into()),
+                        ("n", n.into()),
+                        ("x", self.x.into()),
+                    ],
+                );
             }
             State::Decode => {
                 let Some(ins) = self.memory.get(self.pc) else { return false };
                 match ins {
-                    Instruction::Noop => self.pc += 1,
-                    &Instruction::Addx(n) => self.state = State::Addx(n),
+                    Instruction::Noop => {
+                        self.pc += 1;
+                        tracer.emit("noop", &[("pc", self.pc.into()), ("x", self.x.into())]);
+                    }
+                    &Instruction::Addx(n) => {
+                        self.state = State::Addx(n);
+                        tracer.emit("decode", &[("pc", self.pc.into()), ("n", n.into())]);
+                    }
                 }
             }
         }
@@ -93,7 +112,7 @@ impl Screen {
     }
 }
 
-fn solve(input: &str) -> (Num, Screen) {
+fn solve(input: &str, tracer: &mut impl Tracer) -> (Num, Screen) {
     let instructions = input.lines().map(Instruction::parse).collect::<Vec<_>>();
     let mut ret = 0;
     let mut cpu = Cpu::new(&instructions);
@@ -103,7 +122,7 @@ fn solve(input: &str) -> (Num, Screen) {
             ret += cycle * cpu.x;
         }
         screen.tick(cpu.x);
-        if !cpu.tick() {
+        if !cpu.tick(tracer) {
             break;
         }
     }
@@ -257,7 +276,20 @@ addx -11
 noop
 noop
 noop";
-    let (answer, screen) = solve(input);
+    let mut tracer = Printer::from_args(std::env::args().skip(1));
+    let (answer, screen) = solve(input, &mut tracer);
     println!("{answer}");
     screen.render();
 }
+
+#[test]
+fn trace() {
+    let mut tracer = Printer::new(Vec::new(), None, trace::Format::Text);
+    let (answer, _) = solve("noop\naddx 3\naddx -5", &mut tracer);
+    assert_eq!(answer, 0);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    assert_eq!(
+        trace,
+        "noop pc=1 x=1\ndecode pc=1 n=3\naddx pc=2 n=3 x=4\ndecode pc=2 n=-5\naddx pc=3 n=-5 x=-1\n"
+    );
+}
@@ -1,10 +1,23 @@
+#[path = "../../common/trace.rs"]
+mod trace;
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
+
 use std::cell::RefCell;
+use trace::{Printer, Tracer, Value};
 
+#[cfg(not(any(aoc_checked, aoc_wide)))]
 type Worry = u32;
+#[cfg(aoc_checked)]
+type Worry = numeric::Checked<u32>;
+#[cfg(aoc_wide)]
+type Worry = numeric::Checked<numeric::U256>;
 type Monkeys<'a> = &'a [RefCell<Monkey>];
 
 #[derive(Debug)]
 struct Monkey {
+    id: usize,
     items: Vec<Worry>,
     n_inspected: usize,
     operation: Expr,
@@ -67,6 +80,7 @@ impl Monkey {
         assert_ne!(num, test.if_false);
 
         Self {
+            id: num,
             items,
             n_inspected: 0,
             operation,
@@ -74,19 +88,24 @@ impl Monkey {
         }
     }
 
-    fn turn(&mut self, monkeys: Monkeys<'_>) {
+    fn turn(&mut self, monkeys: Monkeys<'_>, tracer: &mut impl Tracer) {
+        tracer.emit("turn", &[("monkey", self.id.into()), ("n_items", self.items.len().into())]);
         self.n_inspected += self.items.len();
         let mut items = std::mem::take(&mut self.items);
         for item in items.drain(..) {
-            self.distribute(item, monkeys);
+            self.distribute(item, monkeys, tracer);
         }
         self.items = items;
     }
 
-    fn distribute(&mut self, mut item: Worry, monkeys: Monkeys<'_>) {
+    fn distribute(&mut self, mut item: Worry, monkeys: Monkeys<'_>, tracer: &mut impl Tracer) {
         item = self.operation.eval(item);
-        item /= 3;
+        item /= Worry::from(3u8);
         let target = self.test.select(item);
+        tracer.emit(
+            "throw",
+            &[("monkey", self.id.into()), ("worry", Value::Num(&item)), ("to", target.into())],
+        );
         monkeys[target].borrow_mut().items.push(item);
     }
 }
@@ -157,7 +176,7 @@ impl Test {
     }
 
     fn select(&self, item: Worry) -> usize {
-        if item % self.divisor == 0 {
+        if item % self.divisor == Worry::from(0u8) {
             self.if_true
         } else {
             self.if_false
@@ -165,22 +184,22 @@ impl Test {
     }
 }
 
-fn round(monkeys: Monkeys<'_>) {
+fn round(monkeys: Monkeys<'_>, tracer: &mut impl Tracer) {
     for monkey in monkeys {
-        monkey.borrow_mut().turn(monkeys);
+        monkey.borrow_mut().turn(monkeys, tracer);
     }
 }
 
 const N_ROUNDS: usize = 20;
 
-fn solve(input: &str) -> usize {
+fn solve(input: &str, tracer: &mut impl Tracer) -> usize {
     let monkeys = input
         .split("\n\n")
         .enumerate()
         .map(|(i, line)| RefCell::new(Monkey::parse(line, i)))
         .collect::<Vec<_>>();
     for _ in 0..N_ROUNDS {
-        round(&monkeys);
+        round(&monkeys, tracer);
     }
     let mut n_inspected = monkeys
         .iter()
@@ -191,8 +210,7 @@ fn solve(input: &str) -> usize {
     max * second_max
 }
 
-fn main() {
-    let input = "Monkey 0:
+const EXAMPLE: &str = "Monkey 0:
   Starting items: 79, 98
   Operation: new = old * 19
   Test: divisible by 23
@@ -219,5 +237,26 @@ Monkey 3:
   Test: divisible by 17
     If true: throw to monkey 0
     If false: throw to monkey 1";
-    println!("{}", solve(input));
+
+fn main() {
+    let mut tracer = Printer::from_args(std::env::args().skip(1));
+    println!("{}", solve(EXAMPLE, &mut tracer));
+}
+
+#[test]
+fn trace() {
+    let mut tracer = Printer::new(Vec::new(), None, trace::Format::Text);
+    assert_eq!(solve(EXAMPLE, &mut tracer), 10605);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..4],
+        [
+            "turn monkey=0 n_items=2",
+            "throw monkey=0 worry=500 to=3",
+            "throw monkey=0 worry=620 to=3",
+            "turn monkey=1 n_items=4",
+        ]
+    );
+    assert_eq!(lines.iter().filter(|l| l.starts_with("turn")).count(), 4 * N_ROUNDS);
 }
@@ -1,10 +1,23 @@
+#[path = "../../common/trace.rs"]
+mod trace;
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
+
 use std::cell::RefCell;
+use trace::{NoTrace, Tracer, Value};
 
+#[cfg(not(any(aoc_checked, aoc_wide)))]
 type Worry = u64;
+#[cfg(aoc_checked)]
+type Worry = numeric::Checked<u64>;
+#[cfg(aoc_wide)]
+type Worry = numeric::Checked<numeric::U256>;
 type Monkeys<'a> = &'a [RefCell<Monkey>];
 
 #[derive(Debug)]
 struct Monkey {
+    id: usize,
     items: Vec<Worry>,
     n_inspected: usize,
     operation: Expr,
@@ -67,6 +80,7 @@ impl Monkey {
         assert_ne!(num, test.if_false);
 
         Self {
+            id: num,
             items,
             n_inspected: 0,
             operation,
@@ -74,18 +88,29 @@ impl Monkey {
         }
     }
 
-    fn turn(&mut self, monkeys: Monkeys<'_>, global_lcm: Worry) {
+    fn turn(&mut self, monkeys: Monkeys<'_>, global_lcm: Worry, tracer: &mut impl Tracer) {
+        tracer.emit("turn", &[("monkey", self.id.into()), ("n_items", self.items.len().into())]);
         self.n_inspected += self.items.len();
         let mut items = std::mem::take(&mut self.items);
         for item in items.drain(..) {
-            self.distribute(item, monkeys, global_lcm);
+            self.distribute(item, monkeys, global_lcm, tracer);
         }
         self.items = items;
     }
 
-    fn distribute(&mut self, mut item: Worry, monkeys: Monkeys<'_>, global_lcm: Worry) {
+    fn distribute(
+        &mut self,
+        mut item: Worry,
+        monkeys: Monkeys<'_>,
+        global_lcm: Worry,
+        tracer: &mut impl Tracer,
+    ) {
         item = self.operation.eval(item) % global_lcm;
         let target = self.test.select(item);
+        tracer.emit(
+            "throw",
+            &[("monkey", self.id.into()), ("worry", Value::Num(&item)), ("to", target.into())],
+        );
         monkeys[target].borrow_mut().items.push(item);
     }
 }
@@ -156,7 +181,7 @@ impl Test {
     }
 
     fn select(&self, item: Worry) -> usize {
-        if item % self.divisor == 0 {
+        if item % self.divisor == Worry::from(0u8) {
             self.if_true
         } else {
             self.if_false
@@ -164,15 +189,24 @@ impl Test {
     }
 }
 
-fn round(monkeys: Monkeys<'_>, global_lcm: Worry) {
+fn round(monkeys: Monkeys<'_>, global_lcm: Worry, tracer: &mut impl Tracer) {
     for monkey in monkeys {
-        monkey.borrow_mut().turn(monkeys, global_lcm);
+        monkey.borrow_mut().turn(monkeys, global_lcm, tracer);
     }
 }
 
 const N_ROUNDS: usize = 10_000;
 
+/// Reads the monkeys from stdin, `--trace` flags as in `trace`.
+pub fn main() {
+    trace::main(solve_traced);
+}
+
 pub fn solve(input: &str) -> usize {
+    solve_traced(input, &mut NoTrace)
+}
+
+pub fn solve_traced(input: &str, tracer: &mut impl Tracer) -> usize {
     let monkeys = input
         .split("\n\n")
         .enumerate()
@@ -182,7 +216,7 @@ pub fn solve(input: &str) -> usize {
     // (they are in actual test case)
     let global_lcm = monkeys.iter().map(|m| m.borrow().test.divisor).product();
     for _ in 0..N_ROUNDS {
-        round(&monkeys, global_lcm);
+        round(&monkeys, global_lcm, tracer);
     }
     let mut n_inspected = monkeys
         .iter()
@@ -223,4 +257,12 @@ Monkey 3:
     If true: throw to monkey 0
     If false: throw to monkey 1";
     assert_eq!(solve(input), 2713310158);
+
+    let kinds = Some(vec!["turn".to_owned()]);
+    let mut tracer = trace::Printer::new(Vec::new(), kinds, trace::Format::Json);
+    assert_eq!(solve_traced(input, &mut tracer), 2713310158);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4 * N_ROUNDS);
+    assert_eq!(lines[0], r#"{"kind":"turn","monkey":0,"n_items":2}"#);
 }
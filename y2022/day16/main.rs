@@ -1,6 +1,13 @@
+#[path = "../../common/dump.rs"]
+mod dump;
+
+use serde_json::{json, Map, Value as Json};
 use std::collections::HashMap;
 
+/// `dump < input` prints the parsed input as JSON, no arguments solve the
+/// example.
 fn main() {
+    dump::main(dump);
     let input = "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
 Valve BB has flow rate=13; tunnels lead to valves CC, AA
 Valve CC has flow rate=2; tunnels lead to valves DD, BB
@@ -14,6 +21,23 @@ Valve JJ has flow rate=21; tunnel leads to valve II";
     println!("{:?}", solve(input));
 }
 
+/// Parsed valves keyed by name, for checking the parser and external tooling.
+fn dump(input: &str) -> Json {
+    let mut i = Interner::default();
+    let (edges, rates) = parse(input, &mut i);
+    let valves = i
+        .words
+        .iter()
+        .enumerate()
+        .map(|(id, name)| {
+            let tunnels = edges[id].iter().map(|&to| i.words[to]).collect::<Vec<_>>();
+            let valve = json!({ "rate": rates[id], "tunnels": tunnels });
+            (name.to_string(), valve)
+        })
+        .collect::<Map<_, _>>();
+    Json::Object(valves)
+}
+
 type Num = u32;
 type Id = usize;
 type Edges = Vec<Vec<Id>>;
@@ -284,3 +308,20 @@ fn usable_valves(rates: &Rates) -> SmallSet {
         .filter_map(|(i, &rate)| (rate != 0).then_some(i))
         .collect()
 }
+
+#[test]
+fn dump_valves() {
+    let input = "Valve AA has flow rate=0; tunnels lead to valves BB, CC
+Valve BB has flow rate=13; tunnel leads to valve AA
+Valve CC has flow rate=2; tunnel leads to valve AA
+";
+    let dumped = dump::dumped(input, dump);
+    assert_eq!(
+        dumped,
+        json!({
+            "AA": { "rate": 0, "tunnels": ["BB", "CC"] },
+            "BB": { "rate": 13, "tunnels": ["AA"] },
+            "CC": { "rate": 2, "tunnels": ["AA"] },
+        })
+    );
+}
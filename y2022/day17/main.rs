@@ -1,10 +1,16 @@
+#[path = "../../common/trace.rs"]
+mod trace;
+
+use trace::{Printer, Tracer};
+
 fn main() {
     let input = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
-    println!("{}", solve(input, 2022));
-    println!("{}", solve(input, 1000000000000));
+    let mut tracer = Printer::from_args(std::env::args().skip(1));
+    println!("{}", solve(input, 2022, &mut tracer));
+    println!("{}", solve(input, 1000000000000, &mut tracer));
 }
 
-fn solve(input: &str, n_fall: usize) -> usize {
+fn solve(input: &str, n_fall: usize, tracer: &mut impl Tracer) -> usize {
     let (
         Loop {
             fell_starting_loop,
@@ -13,7 +19,7 @@ fn solve(input: &str, n_fall: usize) -> usize {
             height_per_loop,
         },
         history,
-    ) = loop_size(input);
+    ) = loop_size(input, tracer);
     let fell_looping = n_fall - fell_starting_loop;
     let n_loops = fell_looping / loop_size;
     let fell_incomplete_loop = fell_looping % loop_size;
@@ -30,7 +36,7 @@ struct Loop {
     height_per_loop: usize,
 }
 
-fn loop_size(input: &str) -> (Loop, Vec<usize>) {
+fn loop_size(input: &str, tracer: &mut impl Tracer) -> (Loop, Vec<usize>) {
     use std::collections::hash_map::{Entry, HashMap};
 
     let moves = input.bytes().map(Move::parse).collect::<Vec<_>>();
@@ -47,7 +53,7 @@ fn loop_size(input: &str) -> (Loop, Vec<usize>) {
         let move_idx = loop {
             let (idx, &move_) = moves.next().unwrap();
             rock.try_move(move_, &mut chamber);
-            rock = match rock.fall(&mut chamber) {
+            rock = match rock.fall(&mut chamber, tracer) {
                 Fell::Down(rock) => rock,
                 Fell::ToRest => break idx,
             };
@@ -193,9 +199,10 @@ impl FallingRock {
         }
     }
 
-    fn fall(&self, chamber: &mut Chamber) -> Fell {
+    fn fall(&self, chamber: &mut Chamber, tracer: &mut impl Tracer) -> Fell {
         if self.y == 0 {
             chamber.put(self);
+            tracer.emit("rest", &[("x", self.x.into()), ("y", self.y.into())]);
             return Fell::ToRest;
         }
         let mut ret = self.clone();
@@ -207,8 +214,10 @@ impl FallingRock {
             .any(|(row, p)| row.overlaps_with(p))
         {
             chamber.put(self);
+            tracer.emit("rest", &[("x", self.x.into()), ("y", self.y.into())]);
             return Fell::ToRest;
         }
+        tracer.emit("fall", &[("x", ret.x.into()), ("y", ret.y.into())]);
         Fell::Down(ret)
     }
 
@@ -340,3 +349,14 @@ impl Chamber {
         ret
     }
 }
+
+#[test]
+fn trace() {
+    let input = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+    let kinds = Some(vec!["rest".to_owned()]);
+    let mut tracer = Printer::new(Vec::new(), kinds, trace::Format::Text);
+    assert_eq!(solve(input, 2022, &mut tracer), 3068);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(lines[..2], ["rest x=2 y=0", "rest x=2 y=1"]);
+}
@@ -1,3 +1,8 @@
+#[path = "../../common/lines.rs"]
+mod lines;
+
+use std::io::{self, BufRead};
+
 type Num = u32;
 
 #[derive(Clone, Copy)]
@@ -38,7 +43,19 @@ fn solve(s: &str) -> usize {
     s.lines().filter(has_overlap).count()
 }
 
+fn solve_reader(reader: impl BufRead) -> io::Result<usize> {
+    let mut ret = 0;
+    lines::for_each_line(reader, |l| ret += has_overlap(&l) as usize)?;
+    Ok(ret)
+}
+
+/// `main -` reads the assignment pairs from stdin instead.
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("-") {
+        println!("{}", solve_reader(io::stdin().lock()).unwrap());
+        return;
+    }
+
     let input = "35-73,35-82
 9-27,10-80
 25-93,2-42
@@ -1041,3 +1058,12 @@ fn main() {
 11-74,7-12";
     println!("{}", solve(input))
 }
+
+#[test]
+fn reader() {
+    let example = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8\n";
+    assert_eq!(solve(example), 4);
+    assert_eq!(solve_reader(example.as_bytes()).unwrap(), 4);
+    let crlf = example.replace('\n', "\r\n");
+    assert_eq!(solve_reader(crlf.as_bytes()).unwrap(), 4);
+}
@@ -1,4 +1,8 @@
-#![cfg(test)]
+#[path = "../../common/lines.rs"]
+mod lines;
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
 
 type LotNumber = u8;
 type Score = u32;
@@ -24,34 +28,67 @@ fn score(n_common: usize) -> Score {
     (2 as Score).pow(n_common as u32 - 1)
 }
 
-fn solve_both(input: &str) -> (Score, usize) {
-    let mut cards = input
-        .lines()
-        .filter(|l| !l.is_empty())
-        .map(|l| (1, n_common(l)))
-        .collect::<Vec<_>>();
-    let cards = &mut cards[..];
-
-    let first = cards
-        .iter()
-        .map(|&(_times, n_common)| score(n_common))
-        .sum();
+/// Running answers for both parts. Only the extra copies won for the next
+/// few cards are kept, so memory does not grow with the number of cards.
+#[derive(Default)]
+struct Tally {
+    score: Score,
+    n_cards: usize,
+    pending_copies: VecDeque<usize>,
+}
 
-    for i in 0..cards.len() {
-        let (inc, n_common) = cards[i];
-        cards[i + 1..]
+impl Tally {
+    fn add_card(&mut self, n_common: usize) {
+        self.score += score(n_common);
+        let times = 1 + self.pending_copies.pop_front().unwrap_or(0);
+        self.n_cards += times;
+        if self.pending_copies.len() < n_common {
+            self.pending_copies.resize(n_common, 0);
+        }
+        self.pending_copies
             .iter_mut()
             .take(n_common)
-            .for_each(|(times, _)| *times += inc);
+            .for_each(|copies| *copies += times);
+    }
+
+    fn answers(&self) -> (Score, usize) {
+        (self.score, self.n_cards)
     }
-    let second = cards.iter().map(|&(times, _)| times).sum();
+}
 
-    (first, second)
+fn solve_both(input: &str) -> (Score, usize) {
+    let mut tally = Tally::default();
+    input
+        .lines()
+        .filter(|l| !l.is_empty())
+        .for_each(|l| tally.add_card(n_common(l)));
+    tally.answers()
+}
+
+fn solve_both_reader(reader: impl BufRead) -> io::Result<(Score, usize)> {
+    let mut tally = Tally::default();
+    lines::for_each_line(reader, |l| {
+        if !l.is_empty() {
+            tally.add_card(n_common(l));
+        }
+    })?;
+    Ok(tally.answers())
+}
+
+/// `main -` reads the cards from stdin instead.
+pub fn main() {
+    let (first, second) = if std::env::args().nth(1).as_deref() == Some("-") {
+        solve_both_reader(io::stdin().lock()).unwrap()
+    } else {
+        solve_both(INPUT)
+    };
+    println!("{first} {second}");
 }
 
 #[test]
 fn example() {
     assert_eq!(solve_both(INPUT), (13, 30));
+    assert_eq!(solve_both_reader(INPUT.as_bytes()).unwrap(), (13, 30));
 }
 
 const INPUT: &str = "
@@ -1,5 +1,7 @@
-#![cfg(test)]
+#[path = "../../common/dump.rs"]
+mod dump;
 
+use serde_json::{json, Value as Json};
 use std::ops::Range;
 
 type Num = u64;
@@ -167,7 +169,7 @@ fn ranges_from_pairs(seeds: &[Num]) -> Vec<Range<Num>> {
         .collect()
 }
 
-fn solve_both(input: &str) -> (Num, Num) {
+fn parse(input: &str) -> (Vec<Num>, Vec<Map>) {
     let mut parts = input.split("\n\n");
     let seeds = parts
         .next()
@@ -179,6 +181,39 @@ fn solve_both(input: &str) -> (Num, Num) {
         .map(|n| n.parse().unwrap())
         .collect::<Vec<_>>();
     let maps = parts.map(Map::parse).collect::<Vec<_>>();
+    (seeds, maps)
+}
+
+/// Parsed seeds and maps, for checking the parser and external tooling.
+fn dump(input: &str) -> Json {
+    let (seeds, maps) = parse(input);
+    let maps = maps
+        .iter()
+        .map(|m| {
+            m.ranges
+                .iter()
+                .map(|p| {
+                    json!({
+                        "source": [p.source_range.start, p.source_range.end],
+                        "target_start": p.target_start,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    json!({ "seeds": seeds, "maps": maps })
+}
+
+/// `dump < input` prints the parsed input as JSON, plain stdin gets solved.
+pub fn main() {
+    dump::main(dump);
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    let (first, second) = solve_both(&input);
+    println!("{first} {second}");
+}
+
+pub fn solve_both(input: &str) -> (Num, Num) {
+    let (seeds, maps) = parse(input);
 
     let first = seeds
         .iter()
@@ -198,8 +233,18 @@ fn solve_both(input: &str) -> (Num, Num) {
 #[test]
 fn example() {
     assert_eq!(solve_both(INPUT), (35, 46));
+    let dumped = dump::dumped(INPUT, dump);
+    assert_eq!(dumped["seeds"], json!([79, 14, 55, 13]));
+    assert_eq!(
+        dumped["maps"][0],
+        json!([
+            { "source": [50, 98], "target_start": 52 },
+            { "source": [98, 100], "target_start": 50 },
+        ])
+    );
 }
 
+#[cfg(test)]
 const INPUT: &str = "
 seeds: 79 14 55 13
 
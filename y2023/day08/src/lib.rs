@@ -1,4 +1,5 @@
 use num_integer::Integer;
+use serde_json::{json, Value as Json};
 
 use std::collections::HashMap;
 
@@ -9,6 +10,13 @@ pub enum Direction {
 }
 
 impl Direction {
+    fn to_char(self) -> char {
+        match self {
+            Self::Left => 'L',
+            Self::Right => 'R',
+        }
+    }
+
     fn parse_seq(s: &str) -> Vec<Self> {
         s.as_bytes()
             .iter()
@@ -68,6 +76,14 @@ pub fn parse_input(input: &str) -> (Forks, Vec<Direction>) {
     (forks, directions)
 }
 
+/// Parsed input as JSON, for checking the parser and external tooling.
+pub fn dump(forks: &Forks, directions: &[Direction]) -> Json {
+    json!({
+        "directions": directions.iter().map(|d| d.to_char()).collect::<String>(),
+        "forks": forks,
+    })
+}
+
 fn step<'f>(here: &str, forks: &'f Forks, dir: Direction) -> &'f str {
     match (&forks[here], dir) {
         ([left, _], Direction::Left) => left,
@@ -161,6 +177,10 @@ ZZZ = (ZZZ, ZZZ)
 ";
     let (forks, directions) = parse_input(input);
     assert_eq!(solve_first(&forks, &directions), 6);
+    assert_eq!(
+        dump(&forks, &directions).to_string(),
+        r#"{"directions":"LLR","forks":{"AAA":["BBB","BBB"],"BBB":["AAA","ZZZ"],"ZZZ":["ZZZ","ZZZ"]}}"#
+    );
 }
 
 #[test]
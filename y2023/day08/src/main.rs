@@ -0,0 +1,15 @@
+#[path = "../../../common/dump.rs"]
+mod dump;
+
+/// `dump < input` prints the parsed input as JSON, plain stdin gets solved.
+fn main() {
+    dump::main(|input| {
+        let (forks, directions) = aoc::parse_input(input);
+        aoc::dump(&forks, &directions)
+    });
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    let (forks, directions) = aoc::parse_input(&input);
+    let first = aoc::solve_first(&forks, &directions);
+    let second = aoc::solve_second(&forks, &directions);
+    println!("{first} {second}");
+}
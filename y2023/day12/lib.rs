@@ -1,3 +1,7 @@
+#[path = "../../common/memo.rs"]
+mod memo;
+
+use memo::Memo;
 use std::num::NonZeroUsize;
 
 pub type Num = u64;
@@ -179,10 +183,10 @@ fn n_task_solutions(tiles: &[Tile], spec: &Spec) -> Num {
 fn n_task_solutions_memoized<'a>(tiles: &'a [Tile], spec: &'a Spec) -> Num {
     let original = (SubSolution::from_spec(spec), tiles);
     let mut subtasks = vec![original.clone()];
-    let mut memo = std::collections::HashMap::new();
+    let mut memo = Memo::new();
 
     while let Some((sub, tiles)) = subtasks.pop() {
-        if memo.contains_key(&(sub.clone(), tiles)) {
+        if memo.contains(&(sub.clone(), tiles)) {
             continue;
         } else {
             subtasks.push((sub.clone(), tiles));
@@ -226,7 +230,7 @@ fn n_task_solutions_memoized<'a>(tiles: &'a [Tile], spec: &'a Spec) -> Num {
         memo.insert((sub, tiles), subanswer);
     }
 
-    memo[&original]
+    *memo.get(&original).unwrap()
 }
 
 pub fn solve_first(tasks: &[Task]) -> Num {
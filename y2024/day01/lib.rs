@@ -1,19 +1,50 @@
+#[path = "../../common/lines.rs"]
+mod lines;
+
+use std::io::{self, BufRead};
+
 pub type Num = u32;
 const DELIMITER: &str = "   ";
 
+fn parse_line(line: &str) -> (Num, Num) {
+    let mut iter = line.split(DELIMITER).map(|i| i.parse::<Num>().unwrap());
+    (iter.next().unwrap(), iter.next().unwrap())
+}
+
 fn parse(input: &str) -> (Vec<Num>, Vec<Num>) {
     input
         .lines()
         .filter(|l| !l.is_empty())
-        .map(|line| {
-            let mut iter = line.split(DELIMITER).map(|i| i.parse::<Num>().unwrap());
-            (iter.next().unwrap(), iter.next().unwrap())
-        })
+        .map(parse_line)
         .unzip()
 }
 
+/// Both parts need the whole lists, so only the text itself is not kept
+/// in memory.
+fn parse_reader(reader: impl BufRead) -> io::Result<(Vec<Num>, Vec<Num>)> {
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    lines::for_each_line(reader, |line| {
+        if !line.is_empty() {
+            let (l, r) = parse_line(line);
+            left.push(l);
+            right.push(r);
+        }
+    })?;
+    Ok((left, right))
+}
+
 pub fn solve_both(input: &str) -> (Num, Num) {
-    let (mut left, mut right) = parse(input);
+    let (left, right) = parse(input);
+    solve_lists(left, right)
+}
+
+pub fn solve_both_reader(reader: impl BufRead) -> io::Result<(Num, Num)> {
+    let (left, right) = parse_reader(reader)?;
+    Ok(solve_lists(left, right))
+}
+
+fn solve_lists(mut left: Vec<Num>, mut right: Vec<Num>) -> (Num, Num) {
     let answer1 = {
         left.sort_unstable();
         right.sort_unstable();
@@ -42,4 +73,5 @@ fn example() {
 3   3
 ";
     assert_eq!(solve_both(INPUT), (11, 31));
+    assert_eq!(solve_both_reader(INPUT.as_bytes()).unwrap(), (11, 31));
 }
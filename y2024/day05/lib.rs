@@ -1,3 +1,7 @@
+#[path = "../../common/dump.rs"]
+mod dump;
+
+use serde_json::{json, Map, Value as Json};
 use std::collections::{HashMap, HashSet};
 
 type Page = u16;
@@ -38,6 +42,28 @@ fn parse(input: &str) -> (Orderings, Vec<Vec<Page>>) {
     (orderings, updates)
 }
 
+/// Parsed orderings and updates, for checking the parser and external tooling.
+pub fn dump(input: &str) -> Json {
+    let (orderings, updates) = parse(input);
+    let orderings = orderings
+        .iter()
+        .map(|(before, after)| {
+            let mut after = after.iter().copied().collect::<Vec<_>>();
+            after.sort_unstable();
+            (before.to_string(), json!(after))
+        })
+        .collect::<Map<_, _>>();
+    json!({ "orderings": orderings, "updates": updates })
+}
+
+/// `dump < input` prints the parsed input as JSON, plain stdin gets solved.
+pub fn main() {
+    dump::main(dump);
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    let (first, second) = solve_both(&input);
+    println!("{first} {second}");
+}
+
 pub fn solve_both(input: &str) -> (Page, Page) {
     let (orderings, mut updates) = parse(input);
     let order = |a: &_, b: &_| {
@@ -102,4 +128,7 @@ fn example() {
 ";
 
     assert_eq!(solve_both(INPUT), (143, 123));
+    let dumped = dump::dumped(INPUT, dump);
+    assert_eq!(dumped["orderings"]["97"], json!([13, 29, 47, 53, 61, 75]));
+    assert_eq!(dumped["updates"][2], json!([75, 29, 13]));
 }
@@ -1,6 +1,8 @@
-}
+#[path = "../../common/trace.rs"]
+mod trace;
 
 use std::collections::HashSet as Set;
+use trace::{NoTrace, Tracer};
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 struct Position {
@@ -21,6 +23,10 @@ impl Direction {
         use Direction::*;
         [Right, Down, Left, Up][self as usize]
     }
+
+    fn name(self) -> &'static str {
+        ["up", "right", "down", "left"][self as usize]
+    }
 }
 
 struct Bounds {
@@ -48,7 +54,6 @@ impl Guard {
             Left if *col > 0 => *col -= 1,
             _ => return Err(()),
         }
-
         Ok(())
     }
 
@@ -57,6 +62,18 @@ impl Guard {
         ret.step(bounds)?;
         Ok(ret)
     }
+
+    fn trace(&self, kind: &'static str, tracer: &mut impl Tracer) {
+        let Position { row, col } = self.pos;
+        tracer.emit(
+            kind,
+            &[
+                ("row", row.into()),
+                ("col", col.into()),
+                ("dir", self.dir.name().into()),
+            ],
+        );
+    }
 }
 
 fn parse(input: &str) -> (Set<Position>, Guard, Bounds) {
@@ -91,7 +108,7 @@ fn parse(input: &str) -> (Set<Position>, Guard, Bounds) {
 fn loops(obstacles: &Set<Position>, mut guard: Guard, bounds: &Bounds, at: Position) -> bool {
     let mut visited = Set::new();
     loop {
-        let Ok(next) = guard.stepping(&bounds) else {
+        let Ok(next) = guard.stepping(bounds) else {
             break false;
         };
         if next.pos == at || obstacles.contains(&next.pos) {
@@ -105,7 +122,18 @@ fn loops(obstacles: &Set<Position>, mut guard: Guard, bounds: &Bounds, at: Posit
     }
 }
 
+/// Reads the map from stdin, `--trace` flags as in `trace`.
+pub fn main() {
+    trace::main(solve_both_traced);
+}
+
 pub fn solve_both(input: &str) -> (usize, usize) {
+    solve_both_traced(input, &mut NoTrace)
+}
+
+/// Traces the walk of part 1 only: part 2 re-runs it for every candidate
+/// obstacle, which would drown the interesting steps.
+pub fn solve_both_traced(input: &str, tracer: &mut impl Tracer) -> (usize, usize) {
     let (obstacles, start_guard, bounds) = parse(input);
     let mut visited = Set::new();
     let mut guard = start_guard.clone();
@@ -114,11 +142,14 @@ pub fn solve_both(input: &str) -> (usize, usize) {
     while let Ok(next) = guard.stepping(&bounds) {
         if obstacles.contains(&next.pos) {
             guard.dir = guard.dir.turn_right();
+            guard.trace("turn", tracer);
         } else {
             guard = next;
+            guard.trace("step", tracer);
             visited.insert(guard.pos);
         }
     }
+    guard.trace("leave", tracer);
 
     let n_turns = visited.len();
     let n_loops = visited
@@ -129,3 +160,39 @@ pub fn solve_both(input: &str) -> (usize, usize) {
 
     (n_turns, n_loops)
 }
+
+#[test]
+fn example() {
+    let input = "
+....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...
+";
+    assert_eq!(solve_both(input), (41, 6));
+
+    let mut tracer = trace::Printer::new(Vec::new(), None, trace::Format::Text);
+    solve_both_traced(input, &mut tracer);
+    let trace = String::from_utf8(tracer.into_inner()).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[..7],
+        [
+            "step row=5 col=4 dir=up",
+            "step row=4 col=4 dir=up",
+            "step row=3 col=4 dir=up",
+            "step row=2 col=4 dir=up",
+            "step row=1 col=4 dir=up",
+            "turn row=1 col=4 dir=right",
+            "step row=1 col=5 dir=right",
+        ]
+    );
+    assert_eq!(lines.last(), Some(&"leave row=9 col=7 dir=down"));
+    assert!(!lines.iter().any(|l| l.contains("row=0 col=4")));
+}
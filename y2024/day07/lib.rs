@@ -1,6 +1,15 @@
+#[cfg(any(aoc_checked, aoc_wide))]
+#[path = "../../common/numeric.rs"]
+mod numeric;
+
 use std::ops::ControlFlow;
 
+#[cfg(not(any(aoc_checked, aoc_wide)))]
 type Num = u64;
+#[cfg(aoc_checked)]
+type Num = numeric::Checked<u64>;
+#[cfg(aoc_wide)]
+type Num = numeric::Checked<numeric::U256>;
 
 struct Equation {
     target: Num,
@@ -16,23 +25,41 @@ impl Equation {
     }
 }
 
-fn num_concat(mut lhs: Num, rhs: Num) -> Num {
+// Values above the target are `None`: only a multiplication by 0 brings
+// them back, and computing them could overflow.
+
+fn add(acc: Option<Num>, rhs: Num, target: Num) -> Option<Num> {
+    let acc = acc?;
+    (rhs <= target && acc <= target - rhs).then(|| acc + rhs)
+}
+
+fn mul(acc: Option<Num>, rhs: Num, target: Num) -> Option<Num> {
+    if rhs == Num::from(0u8) {
+        return Some(rhs);
+    }
+    let acc = acc?;
+    (acc <= target / rhs).then(|| acc * rhs)
+}
+
+fn num_concat(acc: Option<Num>, rhs: Num, target: Num) -> Option<Num> {
+    let ten = Num::from(10u8);
+    let mut lhs = acc;
     let mut r = rhs;
-    while r != 0 {
-        lhs *= 10;
-        r /= 10;
+    while r != Num::from(0u8) {
+        lhs = mul(lhs, ten, target);
+        r /= ten;
     }
-    lhs + rhs
+    add(lhs, rhs, target)
 }
 
 fn try_recursive<const CONSIDER_CONCAT: bool>(
     target: Num,
-    acc: Num,
+    acc: Option<Num>,
     vals: &[Num],
 ) -> ControlFlow<(), ()> {
     match vals.split_first() {
         None => {
-            if target == acc {
+            if acc == Some(target) {
                 ControlFlow::Break(())
             } else {
                 ControlFlow::Continue(())
@@ -40,10 +67,10 @@ fn try_recursive<const CONSIDER_CONCAT: bool>(
         }
         Some((&next, rest)) => {
             if CONSIDER_CONCAT {
-                try_recursive::<CONSIDER_CONCAT>(target, num_concat(acc, next), rest)?;
+                try_recursive::<CONSIDER_CONCAT>(target, num_concat(acc, next, target), rest)?;
             }
-            try_recursive::<CONSIDER_CONCAT>(target, acc + next, rest)?;
-            try_recursive::<CONSIDER_CONCAT>(target, acc * next, rest)?;
+            try_recursive::<CONSIDER_CONCAT>(target, add(acc, next, target), rest)?;
+            try_recursive::<CONSIDER_CONCAT>(target, mul(acc, next, target), rest)?;
             ControlFlow::Continue(())
         }
     }
@@ -57,6 +84,7 @@ enum Answer {
 
 fn can_be_solved(eq: &Equation) -> Answer {
     let (&first, rest) = eq.operands.split_first().unwrap();
+    let first = (first <= eq.target).then_some(first);
     if try_recursive::<false>(eq.target, first, rest).is_break() {
         Answer::Both
     } else if try_recursive::<true>(eq.target, first, rest).is_break() {
@@ -67,11 +95,12 @@ fn can_be_solved(eq: &Equation) -> Answer {
 }
 
 pub fn solve_both(input: &str) -> (Num, Num) {
+    let zero = Num::from(0u8);
     input
         .lines()
         .filter(|l| !l.is_empty())
         .map(Equation::parse)
-        .fold((0, 0), |acc @ (total, with_concat), eq| {
+        .fold((zero, zero), |acc @ (total, with_concat), eq| {
             let target = eq.target;
             match can_be_solved(&eq) {
                 Answer::No => acc,
@@ -80,3 +109,47 @@ pub fn solve_both(input: &str) -> (Num, Num) {
             }
         })
 }
+
+#[test]
+fn example() {
+    let input = "
+190: 10 19
+3267: 81 40 27
+83: 17 5
+156: 15 6
+7290: 6 8 6 15
+161011: 16 10 13
+192: 17 8 14
+21037: 9 7 18 13
+292: 11 6 16 20
+";
+    assert_eq!(solve_both(input), (Num::from(3749u16), Num::from(11387u16)));
+}
+
+#[test]
+fn huge_operands() {
+    // dead ends far above the target must not overflow
+    let input = "190: 10 19\n5: 999 999 999 999 999 999 999\n";
+    assert_eq!(solve_both(input), (Num::from(190u8), Num::from(190u8)));
+    let input = "18446744073709551615: 99 999999999999 0 18446744073709551615\n";
+    let target = Num::from(u64::MAX);
+    assert_eq!(solve_both(input), (target, target));
+    assert_eq!(
+        solve_both("0: 7 3 0\n5: 2 9 0 5\n"),
+        (Num::from(5u8), Num::from(5u8))
+    );
+}
+
+#[cfg(aoc_checked)]
+#[test]
+#[should_panic(expected = "arithmetic overflow")]
+fn total_overflows() {
+    solve_both("18446744073709551615: 18446744073709551615\n1: 1\n");
+}
+
+#[cfg(aoc_wide)]
+#[test]
+fn total_is_wide() {
+    let (total, _) = solve_both("18446744073709551615: 18446744073709551615\n1: 1\n");
+    assert_eq!(total.to_string(), "18446744073709551616");
+}